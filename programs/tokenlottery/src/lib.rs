@@ -1,13 +1,14 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token_interface::{mint_to, Mint, MintTo, TokenAccount, TokenInterface}
+    token_interface::{mint_to, transfer_checked, Mint, MintTo, TokenAccount, TokenInterface, TransferChecked}
 };
-use switchboard_on_demand::RandomnessAccountData;
+use switchboard_on_demand::{RandomnessAccountData, ON_DEMAND_DEVNET_PID, ON_DEMAND_MAINNET_PID};
 use anchor_spl::metadata::{
     Metadata,
     MetadataAccount,
     CreateMetadataAccountsV3,
+    UpdateMetadataAccountsV2,
     CreateMasterEditionV3,
     SignMetadata,
     SetAndVerifySizedCollectionItem,
@@ -15,6 +16,7 @@ use anchor_spl::metadata::{
     create_metadata_accounts_v3,
     sign_metadata,
     set_and_verify_sized_collection_item,
+    update_metadata_accounts_v2,
     mpl_token_metadata::types::{
             CollectionDetails,
             Creator, 
@@ -23,9 +25,11 @@ use anchor_spl::metadata::{
 };
 
 use anchor_lang::system_program;
+use anchor_lang::solana_program::hash::hashv;
 //on chain address of our program  which is public key of our account  
 declare_id!("9nKa1x4vcnDnPFAQm9VFCrWZgUR4HFyuK69L7kGgXXRC");
 
+//defaults used when a lottery is initialized without its own branding 
 #[constant]
 pub const NAME: &str="Token Lottery";
 
@@ -35,6 +39,38 @@ pub const URI:&str ="https://encrypted-tbn0.gstatic.com/images?q=tbn:ANd9GcRMaBK
 #[constant]
 pub const SYMBOL:&str="lottery";
 
+#[constant]
+pub const MAX_PRIZE_TIERS:u8=10;
+
+//ticket names are the lottery name plus the ticket number and metaplex caps names at 32 
+#[constant]
+pub const MAX_LOTTERY_NAME_LEN:u8=22;
+
+//the collection mint always takes one of the five metaplex creator slots 
+#[constant]
+pub const MAX_CREATORS:u8=4;
+
+//how many times an unrevealed randomness account can be replaced for one draw 
+#[constant]
+pub const MAX_RANDOMNESS_RECOMMITS:u8=8;
+
+//the crank reward is capped at 1% of the pot 
+#[constant]
+pub const MAX_CRANK_REWARD_BPS:u16=100;
+
+#[constant]
+pub const MAX_BENEFICIARIES:u8=4;
+
+#[constant]
+pub const MAX_REDRAWS:u8=5;
+
+//winners of the first draw plus every redraw 
+pub const MAX_DRAWN_TICKETS:usize=MAX_PRIZE_TIERS as usize*(MAX_REDRAWS as usize+1);
+
+//fees and shares of every ticket are expressed in basis points 
+#[constant]
+pub const BPS_DENOMINATOR:u16=10_000;
+
  
 
 //our program will be written there entrypoint of our pro
@@ -48,24 +84,61 @@ pub mod token_lottery {
     use super::*;
     //now we define the function or intruction that the client can call 
     //Context intialize config means which holds the accounts define in that like metadata or all accounts 
-    pub fn configinitialize(ctx: Context<InitializeConfig>,start:u64,end:u64,price:u64) ->Result<()> {
-             ctx.accounts.token_lottery.lottery_start=start;
-             ctx.accounts.token_lottery.lottery_end=end;
-             ctx.accounts.token_lottery.price=price;
-             ctx.accounts.token_lottery.authority=ctx.accounts.payer.key();
-             ctx.accounts.token_lottery.ticket_number=0;     //how mant tickets have been sold 
-             ctx.accounts.token_lottery.winner_chosen=false;
-             ctx.accounts.token_lottery.randomness_account=Pubkey::default();
+    //lottery_id lets one creator run many lotteries side by side (weekly rounds ,raffles ...)
+    pub fn configinitialize(ctx: Context<InitializeConfig>,lottery_id:u64,params:LotteryParams) ->Result<()> {
+             params.validate()?;
+             params.check_roles(ctx.accounts.payer.key())?;
+
+             ctx.accounts.token_lottery.configure(ctx.accounts.payer.key(),lottery_id,ctx.accounts.payer.key(),params);
+             ctx.accounts.token_lottery.round_template=Pubkey::default();
              ctx.accounts.token_lottery.bump=ctx.bumps.token_lottery;
+             ctx.accounts.token_lottery.sol_vault_bump=ctx.bumps.sol_vault;
+
+             //when a payment mint is passed tickets are paid in that token and the pot lives in the vault 
+             ctx.accounts.token_lottery.payment_mint=match &ctx.accounts.payment_mint {
+                Some(payment_mint)=>{
+                    require!(ctx.accounts.vault.is_some(),ErrorCode::MissingPaymentAccounts);
+                    payment_mint.key()
+                },
+                None=>Pubkey::default(),
+             };
+
+             if !ctx.accounts.token_lottery.pays_in_tokens() {
+                fund_sol_vault(&ctx.accounts.payer,&ctx.accounts.sol_vault,&ctx.accounts.system_program)?;
+             }
+
+             emit!(LotteryConfigured{
+                lottery:ctx.accounts.token_lottery.key(),
+                creator:ctx.accounts.token_lottery.creator,
+                lottery_id,
+                payment_mint:ctx.accounts.token_lottery.payment_mint,
+                config:ctx.accounts.token_lottery.config_values(),
+                prize_tiers:ctx.accounts.token_lottery.prize_tiers.clone(),
+             });
         Ok(())
     }
    
      //context is set where we define accounts later  
-   pub fn lotteryinitalize(ctx:Context<InitializeLottery>)->Result<()>{
+   //branding is stored on the lottery so every partner gets its own name ,symbol ,uris and royalties 
+   //rounds started from a template already carry the template branding ,anyone may open them and `branding` is ignored 
+   pub fn lotteryinitalize(ctx:Context<InitializeLottery>,branding:LotteryBranding)->Result<()>{
+       ctx.accounts.token_lottery.require_phase(LotteryPhase::Created)?;
+
+       if ctx.accounts.token_lottery.round_template==Pubkey::default() {
+        require!(ctx.accounts.payer.key()==ctx.accounts.token_lottery.authority,ErrorCode::NotAuthorized);
+        let branding=branding.with_defaults();
+        branding.validate()?;
+        ctx.accounts.token_lottery.branding=branding;
+       }
+       //tickets can be sold once the collection exists 
+       ctx.accounts.token_lottery.phase=LotteryPhase::Open;
+
        //now we create the collection
 
+          let token_lottery_key=ctx.accounts.token_lottery.key();
           let signer_seeds:&[&[&[u8]]]=&[&[      //this  creates the seed info  about to sign on be half of the pda  
             b"collection_mint".as_ref(),          //we created the refrence to the lsit 
+            token_lottery_key.as_ref(),       //every lottery has its own collection 
             &[ctx.bumps.collection_mint],     //bump value for collection of the 
            ]];
 
@@ -100,22 +173,10 @@ pub mod token_lottery {
                 system_program:ctx.accounts.system_program.to_account_info(), 
                 rent:ctx.accounts.rent.to_account_info() 
             },
-            &signer_seeds ,
+            signer_seeds ,
           
         ),
-        DataV2 {
-            name:NAME.to_string(),
-            symbol:SYMBOL.to_string(),
-            uri:URI.to_string(),
-            seller_fee_basis_points:0 ,
-            creators:Some(vec![Creator{
-                address:ctx.accounts.collection_mint.key(),
-            verified:false,
-            share:100
-            }]),
-            collection:None,
-            uses:None
-        },
+        ctx.accounts.token_lottery.branding.collection_data(ctx.accounts.collection_mint.key(),false),
         true,
         true,
         Some(CollectionDetails::V1 { size: 0 })
@@ -138,7 +199,7 @@ pub mod token_lottery {
                 rent:ctx.accounts.rent.to_account_info(),
               },
               
-              &signer_seeds ,
+              signer_seeds ,
         ),
         Some(0),
        )?;
@@ -152,10 +213,18 @@ pub mod token_lottery {
                 creator:ctx.accounts.collection_mint.to_account_info(),
                 metadata:ctx.accounts.metadata.to_account_info()
             },
-            &signer_seeds,
+            signer_seeds,
         )
        )?;
 
+       emit!(CollectionInitialized{
+        lottery:ctx.accounts.token_lottery.key(),
+        collection_mint:ctx.accounts.collection_mint.key(),
+        name:ctx.accounts.token_lottery.branding.name.clone(),
+        symbol:ctx.accounts.token_lottery.branding.symbol.clone(),
+        uri:ctx.accounts.token_lottery.branding.collection_uri.clone(),
+       });
+
     Ok(())
    }
     
@@ -163,29 +232,32 @@ pub mod token_lottery {
 
     pub fn buy_ticket(ctx:Context<InitializeBuyTicket>)->Result<()> {
          let clock=Clock::get()?;
-         let ticket_name=NAME.to_owned() + ctx.accounts.token_lottery.ticket_number.to_string().as_str();
-
 
-         if clock.slot< ctx.accounts.token_lottery.lottery_start || 
-           clock.slot> ctx.accounts.token_lottery.lottery_end {
-            return  Err(ErrorCode::LotteryNotOpen.into());
-           }
+         require!(!ctx.accounts.token_lottery.paused,ErrorCode::LotteryPaused);
+         ctx.accounts.token_lottery.check_sales_open(clock.slot)?;
+         ctx.accounts.token_lottery.check_ticket_supply(1)?;
             
-            system_program::transfer(CpiContext::new(
-                ctx.accounts.system_program.to_account_info(),
-                system_program::Transfer{
-                    from:ctx.accounts.payer.to_account_info(),
-                    to:ctx.accounts.token_lottery.to_account_info()
-                }
-            ),
-        ctx.accounts.token_lottery.price,
-    )?;
-        
+        let received=collect_payment(
+            &ctx.accounts.token_lottery,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+            &ctx.accounts.sol_vault,
+            &ctx.accounts.payment_mint,
+            &ctx.accounts.payer_token_account,
+            &mut ctx.accounts.vault,
+            &ctx.accounts.payment_token_program,
+            ctx.accounts.token_lottery.price,
+        )?;
 
-        ctx.accounts.token_lottery.token_lottery_pot += ctx.accounts.token_lottery.price;
+        let referral_reward=ctx.accounts.token_lottery.deposit(received,ctx.accounts.referral.is_some())?;
+        if let Some(referral)=ctx.accounts.referral.as_mut() {
+            referral.credit(referral_reward)?;
+        }
         
 
+        let token_lottery_key=ctx.accounts.token_lottery.key();
         let signer_seeds:&[&[&[u8]]]=&[&[b"collection_mint".as_ref(),
+        token_lottery_key.as_ref(),
         &[ctx.bumps.collection_mint],
         
         ]];
@@ -197,7 +269,7 @@ pub mod token_lottery {
                 to:ctx.accounts.destination.to_account_info(),
                 authority:ctx.accounts.collection_mint.to_account_info()
             },
-            &signer_seeds,
+            signer_seeds,
         ),
     1
 )?;
@@ -213,17 +285,9 @@ pub mod token_lottery {
                  system_program:ctx.accounts.system_program.to_account_info(),
                  rent:ctx.accounts.rent.to_account_info()
               },
-              &signer_seeds,
+              signer_seeds,
   
-        ),DataV2{
-            name:ticket_name,
-            symbol:SYMBOL.to_string(),
-            uri:URI.to_string(),
-            seller_fee_basis_points:0,
-            creators:None,
-            collection:None,
-            uses:None,
-        },
+        ),ctx.accounts.token_lottery.branding.ticket_data(ctx.accounts.token_lottery.ticket_number),
     
       true,
       true,
@@ -244,7 +308,7 @@ pub mod token_lottery {
             system_program:ctx.accounts.system_program.to_account_info(),
             rent:ctx.accounts.rent.to_account_info(),
         },
-        &signer_seeds,
+        signer_seeds,
        ),
     
      Some(0)
@@ -262,12 +326,28 @@ pub mod token_lottery {
             collection_metadata:ctx.accounts.collection_metadata.to_account_info(),
             collection_master_edition:ctx.accounts.collection_master_edition.to_account_info()
         },
-        &signer_seeds,
+        signer_seeds,
     ),
     None
 )?;
 
 
+        let purchase_record=&mut ctx.accounts.purchase_record;
+        if purchase_record.buyer==Pubkey::default() {
+            purchase_record.lottery=ctx.accounts.token_lottery.key();
+            purchase_record.buyer=ctx.accounts.payer.key();
+            purchase_record.bump=ctx.bumps.purchase_record;
+        }
+        purchase_record.record(&ctx.accounts.token_lottery,1)?;
+
+        emit!(TicketPurchased{
+            lottery:ctx.accounts.token_lottery.key(),
+            buyer:ctx.accounts.payer.key(),
+            ticket_number:ctx.accounts.token_lottery.ticket_number,
+            ticket_mint:ctx.accounts.ticket_mint.key(),
+            price:received,
+        });
+
         ctx.accounts.token_lottery.ticket_number +=1;
         Ok(())
     } 
@@ -286,34 +366,94 @@ pub mod token_lottery {
             return  Err(ErrorCode::        IncorrectRandomessAccount  .into());
          }
 
-         if ctx.accounts.payer.key() != token_lottery.authority {
+         if !token_lottery.can_draw_committed(ctx.accounts.payer.key()) {
             return Err(ErrorCode::    NotAuthorized.into())
          }
 
-        if clock.slot > token_lottery.lottery_end {
-            msg!("lottery statrt  slotv {} ",clock.slot); 
-            msg!("lottery end time slot {}",token_lottery.lottery_end);
-            return Err(ErrorCode::LotteryNotCompelted.into());
-        }
-
-        require!(token_lottery.winner_chosen ==false ,ErrorCode::WinnerChosen ); 
+        //randomness can only be committed after sales closed so this also covers lottery_end 
+        token_lottery.require_phase(LotteryPhase::RandomnessCommitted)?;
 
-       let randomnessaccopunt=RandomnessAccountData::parse(ctx.accounts.randomness_account_data.data.borrow()).unwrap();
+       let randomnessaccopunt=RandomnessAccountData::parse(ctx.accounts.randomness_account_data.data.borrow())
+            .map_err(|_| ErrorCode::InvalidRandomnessAccount)?;
+       require!(randomnessaccopunt.queue==token_lottery.randomness_queue,ErrorCode::IncorrectRandomnessQueue);
+       token_lottery.check_committed_seed(&randomnessaccopunt)?;
 
        let revaled_account=randomnessaccopunt.get_value(&clock).map_err(|_| ErrorCode:: RandomnessNotRevealed)?;
+       drop(randomnessaccopunt);
         
 
         msg!("Randomess  is revealed {}",revaled_account[0]);
 
         msg!("Ticket number {}",token_lottery.ticket_number);
 
+        require!(token_lottery.ticket_number > 0,ErrorCode::NoTicketsSold);
 
-        let randomesresult=revaled_account[0] as u64 % token_lottery.ticket_number;
+       let crank_reward=if token_lottery.redraws > 0 {
+        //a redraw only replaces the unclaimed tiers ,every ticket drawn before is excluded 
+        //prizes were fixed by the first draw so no crank reward is taken again 
+        let open_tiers:Vec<usize>=(0..token_lottery.winners.len()).filter(|tier| !token_lottery.tier_claimed[*tier]).collect();
+        let winners=draw_winners(&revaled_account,token_lottery.ticket_number,open_tiers.len(),&token_lottery.drawn_tickets);
 
-        msg!("winner {}",randomesresult);
-       
-       token_lottery.winner=randomesresult;
-       token_lottery.winner_chosen=true;
+        msg!("redraw {} winners {:?}",token_lottery.redraws,winners);
+
+        for (tier,ticket) in open_tiers.iter().zip(winners.iter().copied()) {
+            token_lottery.winners[*tier]=ticket;
+        }
+        token_lottery.drawn_tickets.extend(winners);
+        0
+       } else {
+        //never more winners than tickets sold ,the remaining tiers are simply not drawn 
+        let winner_count=token_lottery.prize_tiers.len().min(token_lottery.ticket_number as usize);
+        let winners=draw_winners(&revaled_account,token_lottery.ticket_number,winner_count,&[]);
+
+        msg!("winners {:?}",winners);
+
+        //whoever executes the draw is paid from the pot before the tiers are fixed 
+        let crank_reward=token_lottery.crank_reward();
+        token_lottery.token_lottery_pot-=crank_reward;
+
+        token_lottery.tier_claimed=vec![false;winners.len()];
+        token_lottery.drawn_tickets=winners.clone();
+        token_lottery.winners=winners;
+        token_lottery.prize_pool=token_lottery.token_lottery_pot;
+        crank_reward
+       };
+
+       token_lottery.claim_deadline=clock.slot.checked_add(token_lottery.claim_window_slots).ok_or(ErrorCode::MathOverflow)?;
+       token_lottery.phase=LotteryPhase::Drawn;
+
+       if crank_reward > 0 {
+        pay_out(
+            &ctx.accounts.token_lottery,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.sol_vault,
+            &ctx.accounts.system_program,
+            &ctx.accounts.payment_mint,
+            &ctx.accounts.payer_token_account,
+            &ctx.accounts.vault,
+            &ctx.accounts.payment_token_program,
+            crank_reward,
+        )?;
+       }
+
+       //only the first draw feeds the jackpot and rolls for it 
+       let jackpot_prize=if ctx.accounts.token_lottery.redraws==0 && ctx.accounts.token_lottery.jackpot_bps > 0 {
+        settle_jackpot(ctx.accounts,&revaled_account)?
+       } else {
+        0
+       };
+
+       let token_lottery=&ctx.accounts.token_lottery;
+       emit!(WinnersDrawn{
+        lottery:token_lottery.key(),
+        randomness:revaled_account,
+        winners:token_lottery.winners.clone(),
+        prize_pool:token_lottery.prize_pool,
+        crank:ctx.accounts.payer.key(),
+        crank_reward,
+        redraw:token_lottery.redraws,
+        jackpot_prize,
+       });
 
 
         Ok(())
@@ -324,341 +464,3349 @@ pub mod token_lottery {
         let clock=Clock::get()?;
         let token_lottery=&mut ctx.accounts.token_lottery ;
 
-        if ctx.accounts.payer.key() != token_lottery.authority {
+        if !token_lottery.can_draw(ctx.accounts.payer.key()) {
             return Err(ErrorCode::NotAuthorized.into());
 
         }
+        require!(!token_lottery.abandoned_committers.contains(&ctx.accounts.payer.key()),ErrorCode::CommitterBarred);
 
+        token_lottery.close_sales_if_ended(clock.slot);
+        require!(token_lottery.phase != LotteryPhase::Open,ErrorCode::LotteryNotCompelted);
+        token_lottery.require_phase(LotteryPhase::SalesClosed)?;
+        require!(token_lottery.ticket_number >= token_lottery.min_tickets,ErrorCode::MinTicketsNotReached);
+        //min_tickets may be 0 ,a draw over no tickets could never complete 
+        require!(token_lottery.ticket_number > 0,ErrorCode::NoTicketsSold);
 
-        let randomess_account_reveal=RandomnessAccountData::parse(ctx.accounts.randomness_account_data.data.borrow()).unwrap();
-        
 
-        if randomess_account_reveal.seed_slot != clock.slot -1 {
-            return Err(ErrorCode::    RandomnessNotRevealed.into());
-        }
+        let randomess_account_reveal=RandomnessAccountData::parse(ctx.accounts.randomness_account_data.data.borrow())
+            .map_err(|_| ErrorCode::InvalidRandomnessAccount)?;
+
+        token_lottery.check_fresh_commitment(&randomess_account_reveal,clock.slot)?;
           
          
           token_lottery.randomness_account=ctx.accounts.randomness_account_data.key();
+          token_lottery.randomness_committed_slot=clock.slot;
+          token_lottery.randomness_seed_slot=randomess_account_reveal.seed_slot;
+          token_lottery.randomness_committer=ctx.accounts.payer.key();
+          token_lottery.phase=LotteryPhase::RandomnessCommitted;
+
+          emit!(RandomnessCommitted{
+            lottery:token_lottery.key(),
+            randomness_account:token_lottery.randomness_account,
+            seed_slot:randomess_account_reveal.seed_slot,
+          });
        
         Ok(() )
     } 
 
-    pub fn claim_prize(ctx:Context<InitializeClaimPrize>)->Result<()>{
+    //escape hatch when the oracle never reveals ,anyone may swap in a fresh randomness account once the
+    //reveal timeout passed ,revealed randomness can never be replaced and abandoned accounts can never come back 
+    //the gateway shows a reveal to whoever asks before it lands on chain ,so a committer that held one back
+    //may know the outcome ,every committer of an abandoned account is barred from committing or drawing again 
+    pub fn recommit_randomness(ctx:Context<InitializeRecommitRandomness>)->Result<()> {
+        let clock=Clock::get()?;
+        let token_lottery=&mut ctx.accounts.token_lottery;
+
+        token_lottery.require_phase(LotteryPhase::RandomnessCommitted)?;
+        let payer=ctx.accounts.payer.key();
+        require!(
+            payer != token_lottery.randomness_committer && !token_lottery.abandoned_committers.contains(&payer),
+            ErrorCode::CommitterBarred
+        );
+        require!(ctx.accounts.abandoned_randomness_account_data.key()==token_lottery.randomness_account,ErrorCode::IncorrectRandomessAccount);
+
+        token_lottery.check_reveal_timeout(clock.slot)?;
+
+        //a revealed value is public ,replacing it would let whoever saw it pick a different outcome 
+        let abandoned=RandomnessAccountData::parse(ctx.accounts.abandoned_randomness_account_data.data.borrow())
+            .map_err(|_| ErrorCode::InvalidRandomnessAccount)?;
+        token_lottery.check_committed_seed(&abandoned)?;
+        require!(abandoned.reveal_slot==0,ErrorCode::RandomnessRevealed);
+
+        let new_randomness_account=ctx.accounts.randomness_account_data.key();
+        require!(
+            new_randomness_account != token_lottery.randomness_account &&
+            !token_lottery.abandoned_randomness.contains(&new_randomness_account),
+            ErrorCode::IncorrectRandomessAccount
+        );
+        require!(token_lottery.abandoned_randomness.len() < MAX_RANDOMNESS_RECOMMITS as usize,ErrorCode::TooManyRecommits);
+
+        let randomness=RandomnessAccountData::parse(ctx.accounts.randomness_account_data.data.borrow())
+            .map_err(|_| ErrorCode::InvalidRandomnessAccount)?;
+        token_lottery.check_fresh_commitment(&randomness,clock.slot)?;
+
+        let abandoned_account=token_lottery.randomness_account;
+        token_lottery.abandoned_randomness.push(abandoned_account);
+        let abandoned_committer=token_lottery.randomness_committer;
+        token_lottery.abandoned_committers.push(abandoned_committer);
+        token_lottery.randomness_committer=payer;
+        token_lottery.randomness_account=new_randomness_account;
+        token_lottery.randomness_committed_slot=clock.slot;
+        token_lottery.randomness_seed_slot=randomness.seed_slot;
+
+        emit!(RandomnessRecommitted{
+            lottery:token_lottery.key(),
+            abandoned_randomness_account:abandoned_account,
+            randomness_account:new_randomness_account,
+            seed_slot:randomness.seed_slot,
+        });
+
+        Ok(())
+    }
+
+    //each winning ticket claims its own tier 
+    pub fn claim_prize(ctx:Context<InitializeClaimPrize>,tier:u8)->Result<()>{
                   
-         msg!("Winner Chosen :{}" ,ctx.accounts.token_lottery.winner_chosen);
-         require!(ctx.accounts.token_lottery.winner_chosen,ErrorCode::    WinnerNotChosen);
+         msg!("Lottery phase :{:?}" ,ctx.accounts.token_lottery.phase);
+         require!(!ctx.accounts.token_lottery.paused,ErrorCode::LotteryPaused);
+
+         let clock=Clock::get()?;
+         let tier=tier as usize;
+         let prize=ctx.accounts.token_lottery.claim_tier(tier,clock.slot)?;
 
 
          require!(ctx.accounts.metadata.collection.as_ref().unwrap().verified,ErrorCode::TicketNotResolved);
 
          require!(ctx.accounts.metadata.collection.as_ref().unwrap().key== ctx.accounts.collection_mint.key(),ErrorCode::IncorrectTicket);
 
-         let ticket_name=NAME.to_owned() + &ctx.accounts.token_lottery.winner.to_string();
-         let metadata=ctx.accounts.metadata.name.replace("\u{0}", "");
+         //the ticket mint is derived from the winning ticket number ,so the name is not checked 
+         //and tickets minted before a rebrand stay claimable 
+         require!(ctx.accounts.destination.amount > 0 ,ErrorCode::IncorrectTicket);
+
+         msg!("Tier {} prize {}",tier,prize);
+
+         pay_out(
+            &ctx.accounts.token_lottery,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.sol_vault,
+            &ctx.accounts.system_program,
+            &ctx.accounts.payment_mint,
+            &ctx.accounts.payer_token_account,
+            &ctx.accounts.vault,
+            &ctx.accounts.payment_token_program,
+            prize,
+         )?;
+
+         emit!(PrizeClaimed{
+            lottery:ctx.accounts.token_lottery.key(),
+            winner:ctx.accounts.payer.key(),
+            tier:tier as u8,
+            ticket_number:ctx.accounts.token_lottery.winners[tier],
+            amount:prize,
+         });
+       
+        Ok(())
+    }
+
+    //unwinds a lottery that will never be drawn ,ticket holders then get their price back with refund_ticket 
+    pub fn cancel_lottery(ctx:Context<InitializeCancelLottery>)->Result<()> {
+        let clock=Clock::get()?;
+        let token_lottery=&mut ctx.accounts.token_lottery;
+
+        require!(ctx.accounts.payer.key()==token_lottery.authority,ErrorCode::NotAuthorized);
+        token_lottery.close_sales_if_ended(clock.slot);
+        //once a redraw started some tiers may already be paid ,refunds are no longer possible 
+        require!(token_lottery.phase.can_cancel() && token_lottery.redraws==0,ErrorCode::InvalidLotteryPhase);
+
+        //the gateway shows a reveal before it lands on chain ,an authority holding tickets could read the outcome
+        //and cancel a losing draw ,so a committed draw is only cancelled once no replacement can be committed 
+        if token_lottery.phase==LotteryPhase::RandomnessCommitted {
+            require!(token_lottery.abandoned_randomness.len()==MAX_RANDOMNESS_RECOMMITS as usize,ErrorCode::RecommitsAvailable);
+            token_lottery.check_reveal_timeout(clock.slot)?;
+
+            let randomness_account_data=ctx.accounts.randomness_account_data.as_ref().ok_or(ErrorCode::InvalidRandomnessAccount)?;
+            require!(randomness_account_data.key()==token_lottery.randomness_account,ErrorCode::IncorrectRandomessAccount);
+            let randomness=RandomnessAccountData::parse(randomness_account_data.data.borrow())
+                .map_err(|_| ErrorCode::InvalidRandomnessAccount)?;
+            token_lottery.check_committed_seed(&randomness)?;
+            require!(randomness.reveal_slot==0,ErrorCode::RandomnessRevealed);
+        }
 
-         msg!("Ticket name {}",ticket_name);
-         msg!("Metadata {}",metadata);
+        token_lottery.cancel();
+        msg!("Lottery {} cancelled",token_lottery.lottery_id);
 
+        emit!(LotteryCancelled{
+            lottery:token_lottery.key(),
+            authority:ctx.accounts.payer.key(),
+            pot:token_lottery.token_lottery_pot,
+        });
 
-         require!(metadata ==ticket_name,ErrorCode::IncorrectTicket);
-         require!(ctx.accounts.destination.amount > 0 ,ErrorCode::IncorrectTicket);
+        Ok(())
+    }
 
-         **ctx.accounts.token_lottery.to_account_info().try_borrow_mut_lamports() ? -=ctx.accounts.token_lottery.token_lottery_pot;
-         **ctx.accounts.payer.try_borrow_mut_lamports() ?= ctx.accounts.token_lottery.token_lottery_pot;
+    //the ticket_refund marker is created here so the same ticket can never be refunded twice 
+    pub fn refund_ticket(ctx:Context<InitializeRefundTicket>,ticket_number:u64)->Result<()> {
+        let clock=Clock::get()?;
+        let refund=ctx.accounts.token_lottery.take_refund(1,clock.slot)?;
+
+        require!(ctx.accounts.metadata.collection.as_ref().ok_or(ErrorCode::IncorrectTicket)?.verified,ErrorCode::TicketNotResolved);
+        require!(ctx.accounts.metadata.collection.as_ref().ok_or(ErrorCode::IncorrectTicket)?.key==ctx.accounts.collection_mint.key(),ErrorCode::IncorrectTicket);
+        require!(ctx.accounts.destination.amount > 0,ErrorCode::IncorrectTicket);
+
+        pay_out(
+            &ctx.accounts.token_lottery,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.sol_vault,
+            &ctx.accounts.system_program,
+            &ctx.accounts.payment_mint,
+            &ctx.accounts.payer_token_account,
+            &ctx.accounts.vault,
+            &ctx.accounts.payment_token_program,
+            refund,
+        )?;
 
-         ctx.accounts.token_lottery.token_lottery_pot=0;
+        ctx.accounts.ticket_refund.ticket_number=ticket_number;
+        ctx.accounts.ticket_refund.bump=ctx.bumps.ticket_refund;
 
+        msg!("Ticket {} refunded {}",ticket_number,refund);
+
+        emit!(TicketRefunded{
+            lottery:ctx.accounts.token_lottery.key(),
+            owner:ctx.accounts.payer.key(),
+            ticket_number,
+            amount:refund,
+        });
 
-     
-       
         Ok(())
     }
 
+    //bulk entries skip the nft ,the bundle account records a contiguous range of ticket numbers 
+    pub fn buy_tickets(ctx:Context<InitializeBuyTickets>,quantity:u64)->Result<()> {
+        let clock=Clock::get()?;
 
-}
+        require!(quantity > 0,ErrorCode::InvalidTicketQuantity);
+        require!(!ctx.accounts.token_lottery.paused,ErrorCode::LotteryPaused);
+        ctx.accounts.token_lottery.check_sales_open(clock.slot)?;
+        ctx.accounts.token_lottery.check_ticket_supply(quantity)?;
+
+        let cost=ctx.accounts.token_lottery.price.checked_mul(quantity).ok_or(ErrorCode::MathOverflow)?;
+        let received=collect_payment(
+            &ctx.accounts.token_lottery,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+            &ctx.accounts.sol_vault,
+            &ctx.accounts.payment_mint,
+            &ctx.accounts.payer_token_account,
+            &mut ctx.accounts.vault,
+            &ctx.accounts.payment_token_program,
+            cost,
+        )?;
 
-#[derive(Accounts)]   //attribute for the account instruction
-//accounts we setup config function 
-pub struct InitializeConfig<'info> {
+        let purchase_record=&mut ctx.accounts.purchase_record;
+        if purchase_record.buyer==Pubkey::default() {
+            purchase_record.lottery=ctx.accounts.token_lottery.key();
+            purchase_record.buyer=ctx.accounts.payer.key();
+            purchase_record.bump=ctx.bumps.purchase_record;
+        }
+        purchase_record.record(&ctx.accounts.token_lottery,quantity)?;
+
+        let token_lottery=&mut ctx.accounts.token_lottery;
+        let ticket_bundle=&mut ctx.accounts.ticket_bundle;
+        ticket_bundle.lottery=token_lottery.key();
+        ticket_bundle.owner=ctx.accounts.payer.key();
+        ticket_bundle.first_ticket=token_lottery.ticket_number;
+        ticket_bundle.quantity=quantity;
+        ticket_bundle.refunded=false;
+        ticket_bundle.bump=ctx.bumps.ticket_bundle;
+
+        let referral_reward=token_lottery.deposit(received,ctx.accounts.referral.is_some())?;
+        if let Some(referral)=ctx.accounts.referral.as_mut() {
+            referral.credit(referral_reward)?;
+        }
+        token_lottery.ticket_number +=quantity;
 
-     #[account(mut)]
-     pub payer:Signer <'info>,   //we used signer means the accounts should sign the transaction
-     #[account(
-        init,       //we use the inti attribute to init the account  
-        payer=payer,      //this means that payer account will pay for the account instruction 
-        space=8+TokenLottery::INIT_SPACE,    //8 means resverd space for account discr    token lottery means that the additiona space for the 
-        seeds=[b"token_lottery".as_ref()],    //here we init the pda one  seed is static 
-        bump      //bump auto by anchor  
-     )]
+        msg!("Tickets {}..{} bought",ticket_bundle.first_ticket,token_lottery.ticket_number);
 
-     pub token_lottery:Box<Account<'info,TokenLottery>> ,   //we created the heap   
+        emit!(TicketsPurchased{
+            lottery:token_lottery.key(),
+            buyer:ctx.accounts.payer.key(),
+            ticket_bundle:ticket_bundle.key(),
+            first_ticket:ticket_bundle.first_ticket,
+            quantity,
+            cost:received,
+        });
 
+        Ok(())
+    }
 
-     pub system_program:Program<'info,System>
-}
- 
+    pub fn claim_bundle_prize(ctx:Context<InitializeClaimBundlePrize>,tier:u8)->Result<()> {
+        require!(!ctx.accounts.token_lottery.paused,ErrorCode::LotteryPaused);
 
-#[derive(Accounts)]
-pub struct InitializeLottery<'info> {
-    #[account(mut)]
-    pub payer:Signer<'info> ,
+        let clock=Clock::get()?;
+        let tier=tier as usize;
+        let prize=ctx.accounts.token_lottery.claim_tier(tier,clock.slot)?;
+
+        let winner=ctx.accounts.token_lottery.winners[tier];
+        require!(ctx.accounts.ticket_bundle.contains(winner),ErrorCode::IncorrectTicket);
+
+        msg!("Tier {} prize {}",tier,prize);
+
+        pay_out(
+            &ctx.accounts.token_lottery,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.sol_vault,
+            &ctx.accounts.system_program,
+            &ctx.accounts.payment_mint,
+            &ctx.accounts.payer_token_account,
+            &ctx.accounts.vault,
+            &ctx.accounts.payment_token_program,
+            prize,
+        )?;
 
-   //this account is init as spl token mint  
-    #[account(
-     init,
-     payer=payer,
-     mint::decimals=0,   //the token will produce the token mint for th 0decimal space 
-     mint::authority=collection_mint,    //set to pda means giving the authority to the over mint and freeze 
-     mint::freeze_authority=collection_mint,
-    seeds=[b"collection_mint".as_ref()],
-    bump
-)]
+        emit!(PrizeClaimed{
+            lottery:ctx.accounts.token_lottery.key(),
+            winner:ctx.accounts.payer.key(),
+            tier:tier as u8,
+            ticket_number:winner,
+            amount:prize,
+        });
 
-    pub collection_mint:Box<InterfaceAccount<'info,Mint>>,   
+        Ok(())
+    }
 
-  /// CHECK: This account will be initialized by the metaplex program
-    #[account(mut)]
-    pub metadata:UncheckedAccount<'info> ,
-  /// CHECK: This account will be initialized by the metaplex program
-    #[account(mut)] 
-    pub master_edition:UncheckedAccount<'info>,
+    pub fn refund_bundle(ctx:Context<InitializeRefundBundle>)->Result<()> {
+        let clock=Clock::get()?;
 
-      #[account(
-        init_if_needed,   //only init if account does not init  
-        payer=payer,
-        seeds=[b"collection_token_account".as_ref()],
-        bump,
-        token::mint=collection_mint,   //which account we habe to mint  
-        token::authority=collection_token_account
-      )]
+        require!(!ctx.accounts.ticket_bundle.refunded,ErrorCode::TicketAlreadyRefunded);
+        let refund=ctx.accounts.token_lottery.take_refund(ctx.accounts.ticket_bundle.quantity,clock.slot)?;
+
+        pay_out(
+            &ctx.accounts.token_lottery,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.sol_vault,
+            &ctx.accounts.system_program,
+            &ctx.accounts.payment_mint,
+            &ctx.accounts.payer_token_account,
+            &ctx.accounts.vault,
+            &ctx.accounts.payment_token_program,
+            refund,
+        )?;
 
-    //this account holds the token minted by collection mint 
-     pub collection_token_account:Box<InterfaceAccount<'info,TokenAccount>>,
+        ctx.accounts.ticket_bundle.refunded=true;
+        msg!("Bundle starting at {} refunded {}",ctx.accounts.ticket_bundle.first_ticket,refund);
 
+        emit!(BundleRefunded{
+            lottery:ctx.accounts.token_lottery.key(),
+            owner:ctx.accounts.payer.key(),
+            ticket_bundle:ctx.accounts.ticket_bundle.key(),
+            first_ticket:ctx.accounts.ticket_bundle.first_ticket,
+            quantity:ctx.accounts.ticket_bundle.quantity,
+            amount:refund,
+        });
 
+        Ok(())
+    }
 
-    pub associated_token_program:Program<'info,AssociatedToken>,
-    pub token_metadata_program:Program<'info,Metadata>,
-    pub system_program:Program<'info,System>,
-    pub token_program:Interface<'info,TokenInterface>,
-    pub rent:Sysvar<'info,Rent>,
+    //rebrands the collection ,tickets minted from now on use the new branding too 
+    pub fn update_collection_metadata(ctx:Context<InitializeUpdateCollectionMetadata>,branding:LotteryBranding)->Result<()> {
+        require!(ctx.accounts.payer.key()==ctx.accounts.token_lottery.authority,ErrorCode::NotAuthorized);
+        //a finished lottery keeps the collection it was played under 
+        require!(!matches!(ctx.accounts.token_lottery.phase,LotteryPhase::Settled | LotteryPhase::Cancelled),ErrorCode::InvalidLotteryPhase);
+
+        let branding=branding.with_defaults();
+        branding.validate()?;
+        ctx.accounts.token_lottery.branding=branding;
+
+        let token_lottery_key=ctx.accounts.token_lottery.key();
+        let signer_seeds:&[&[&[u8]]]=&[&[
+            b"collection_mint".as_ref(),
+            token_lottery_key.as_ref(),
+            &[ctx.bumps.collection_mint],
+        ]];
 
+        //the collection mint was verified as creator by sign_metadata in lotteryinitalize 
+        update_metadata_accounts_v2(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_metadata_program.to_account_info(),
+                UpdateMetadataAccountsV2{
+                    metadata:ctx.accounts.metadata.to_account_info(),
+                    update_authority:ctx.accounts.collection_mint.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            None,
+            Some(ctx.accounts.token_lottery.branding.collection_data(ctx.accounts.collection_mint.key(),true)),
+            None,
+            None,
+        )?;
 
-}
+        emit!(CollectionUpdated{
+            lottery:ctx.accounts.token_lottery.key(),
+            collection_mint:ctx.accounts.collection_mint.key(),
+            name:ctx.accounts.token_lottery.branding.name.clone(),
+            symbol:ctx.accounts.token_lottery.branding.symbol.clone(),
+            uri:ctx.accounts.token_lottery.branding.collection_uri.clone(),
+        });
 
-#[derive(Accounts)] 
-pub struct InitializeBuyTicket<'info> {
-    #[account(mut)] 
-    pub payer:Signer<'info> ,
+        Ok(())
+    }
 
-    #[account(mut,
-     seeds=[b"token_lottery".as_ref()] ,
-     bump=token_lottery.bump
-    )]
+    //first half of the authority rotation ,nothing changes until the new key accepts 
+    pub fn propose_authority(ctx:Context<InitializeProposeAuthority>,new_authority:Pubkey)->Result<()> {
+        let token_lottery=&mut ctx.accounts.token_lottery;
+        require!(ctx.accounts.payer.key()==token_lottery.authority,ErrorCode::NotAuthorized);
+        //the draw operator can never become admin ,same rule as set_roles 
+        require!(new_authority != token_lottery.draw_operator,ErrorCode::RolesNotSeparated);
 
-    pub token_lottery:Account<'info,TokenLottery> ,
+        token_lottery.pending_authority=new_authority;
+        msg!("Authority {} proposed",new_authority);
 
+        emit!(AuthorityProposed{
+            lottery:token_lottery.key(),
+            authority:token_lottery.authority,
+            pending_authority:new_authority,
+        });
 
-    #[account(
-        init,
-        payer=payer,
-        mint::decimals=0,
-        mint::authority=collection_mint,
-        mint::freeze_authority=collection_mint,
-        mint::token_program=token_program,
-        seeds=[token_lottery.ticket_number.to_le_bytes().as_ref()],
-        bump
+        Ok(())
+    }
 
-    )]
-    pub ticket_mint:InterfaceAccount<'info,Mint> ,
+    pub fn accept_authority(ctx:Context<InitializeAcceptAuthority>)->Result<()> {
+        let token_lottery=&mut ctx.accounts.token_lottery;
+        require!(token_lottery.pending_authority != Pubkey::default(),ErrorCode::NoPendingAuthority);
+        require!(ctx.accounts.payer.key()==token_lottery.pending_authority,ErrorCode::NotAuthorized);
+        //set_roles may have moved the draw operator onto the pending key since the proposal 
+        require!(token_lottery.pending_authority != token_lottery.draw_operator,ErrorCode::RolesNotSeparated);
 
-    #[account(
-        init,
-        payer=payer,
-        associated_token::mint=ticket_mint,
-        associated_token::authority=payer,
-        associated_token::token_program=token_program
-    )]
-    pub destination:InterfaceAccount<'info,TokenAccount>,
-   
+        let previous_authority=token_lottery.authority;
+        token_lottery.authority=token_lottery.pending_authority;
+        token_lottery.pending_authority=Pubkey::default();
+        msg!("Authority {} accepted",token_lottery.authority);
 
+        emit!(AuthorityAccepted{
+            lottery:token_lottery.key(),
+            previous_authority,
+            authority:token_lottery.authority,
+        });
 
-      
-    /// CHECK: This account will be initialized by the metaplex program
-     #[account(mut,
-     seeds=[b"metadata",token_metadata_program.key().as_ref(),
-     ticket_mint.key().as_ref()],
- 
-     seeds::program=token_metadata_program.key(),
-     bump,
-    )]
-     pub metadata:UncheckedAccount<'info> ,
+        Ok(())
+    }
 
+    //the draw operator only runs commit_winner/choose_winner and can never touch config or funds 
+    pub fn set_roles(ctx:Context<InitializeSetRoles>,draw_operator:Pubkey,treasurer:Pubkey,guardian:Pubkey)->Result<()> {
+        let token_lottery=&mut ctx.accounts.token_lottery;
+        require!(ctx.accounts.payer.key()==token_lottery.authority,ErrorCode::NotAuthorized);
+        require!(roles_separated(token_lottery.authority,draw_operator,treasurer),ErrorCode::RolesNotSeparated);
 
+        token_lottery.draw_operator=draw_operator;
+        token_lottery.treasurer=treasurer;
+        token_lottery.guardian=guardian;
 
-    /// CHECK: This account will be initialized by the metaplex program
-     #[account(mut,
-    seeds=[b"metadata",token_metadata_program.key().as_ref(),
-      ticket_mint.key().as_ref(),b"edition" ],
-      bump,
-     seeds::program=token_metadata_program.key()
-)] 
-     pub master_edition:UncheckedAccount<'info>,
-     
-      /// CHECK: This account will be initialized by the metaplex program   
-      #[account(mut,
-       seeds=[b"metadata",token_metadata_program.key().as_ref(),collection_mint.key().as_ref()],
-       bump,
-       seeds::program=token_metadata_program.key()
-    )]
-      pub collection_metadata:UncheckedAccount<'info> ,
+        emit!(RolesUpdated{
+            lottery:token_lottery.key(),
+            draw_operator,
+            treasurer,
+            guardian,
+        });
 
-        
-       /// CHECK: This account will be initialized by the metaplex program
-       #[account(mut,
-       seeds=[b"metadata",token_metadata_program.key().as_ref(),
-       collection_mint.key().as_ref(),b"edition"],
-       bump,
-       seeds::program=token_metadata_program.key(),
-    )]
+        Ok(())
+    }
 
-     pub collection_master_edition:UncheckedAccount<'info>,
+    //recurring rounds ,each round is a lottery with the template as creator and the round number as lottery_id 
+    pub fn create_round_template(
+        ctx:Context<InitializeCreateRoundTemplate>,
+        template_id:u64,
+        duration_slots:u64,
+        params:LotteryParams,
+        branding:LotteryBranding,
+    )->Result<()> {
+        params.validate()?;
+        //every round gets the template authority ,so the roles are checked against it once here 
+        params.check_roles(ctx.accounts.payer.key())?;
+        require!(duration_slots > 0,ErrorCode::InvalidLotteryWindow);
+        let branding=branding.with_defaults();
+        branding.validate()?;
+
+        let round_template=&mut ctx.accounts.round_template;
+        round_template.authority=ctx.accounts.payer.key();
+        round_template.template_id=template_id;
+        round_template.duration_slots=duration_slots;
+        round_template.params=params;
+        round_template.branding=branding;
+        round_template.payment_mint=ctx.accounts.payment_mint.as_ref().map(|payment_mint| payment_mint.key()).unwrap_or_default();
+        round_template.round=0;
+        round_template.current_lottery=Pubkey::default();
+        round_template.bump=ctx.bumps.round_template;
+
+        emit!(RoundTemplateCreated{
+            round_template:round_template.key(),
+            authority:round_template.authority,
+            template_id,
+            duration_slots,
+            payment_mint:round_template.payment_mint,
+        });
 
+        Ok(())
+    }
 
+    //permissionless once the previous round settled ,whatever is left in its pot seeds the new round 
+    //a cancelled previous round keeps its pot for refunds 
+    pub fn start_next_round(ctx:Context<InitializeStartNextRound>)->Result<()> {
+        let clock=Clock::get()?;
+        let round_template=&ctx.accounts.round_template;
+        let round=round_template.round.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+
+        let seed=match ctx.accounts.previous_lottery.as_mut() {
+            Some(previous_lottery)=>{
+                //a round that sold too few or no tickets can never draw ,it falls into refunds here
+                //instead of waiting for a holder or the authority so the series keeps running 
+                if previous_lottery.is_undersubscribed(clock.slot) {
+                    previous_lottery.cancel();
+                }
+                require!(
+                    matches!(previous_lottery.phase,LotteryPhase::Settled | LotteryPhase::Cancelled),
+                    ErrorCode::RoundNotSettled
+                );
+                //a cancelled round refunds its buyers but passes on what it was seeded with 
+                let seed=if previous_lottery.phase==LotteryPhase::Settled {
+                    previous_lottery.token_lottery_pot
+                } else {
+                    previous_lottery.seed_jackpot
+                };
+                previous_lottery.token_lottery_pot-=seed;
+                previous_lottery.seed_jackpot=0;
+                seed
+            },
+            None=>{
+                require!(round_template.round==0,ErrorCode::IncorrectPreviousRound);
+                0
+            },
+        };
+
+        let mut params=round_template.params.clone();
+        params.start=clock.slot;
+        params.end=clock.slot.checked_add(round_template.duration_slots).ok_or(ErrorCode::MathOverflow)?;
+
+        let token_lottery=&mut ctx.accounts.token_lottery;
+        token_lottery.configure(round_template.key(),round,round_template.authority,params);
+        token_lottery.round_template=round_template.key();
+        token_lottery.branding=round_template.branding.clone();
+        token_lottery.token_lottery_pot=seed;
+        token_lottery.seed_jackpot=seed;
+        token_lottery.bump=ctx.bumps.token_lottery;
+        token_lottery.sol_vault_bump=ctx.bumps.sol_vault;
+        token_lottery.payment_mint=round_template.payment_mint;
+        if token_lottery.pays_in_tokens() {
+            require!(ctx.accounts.vault.is_some(),ErrorCode::MissingPaymentAccounts);
+        } else {
+            fund_sol_vault(&ctx.accounts.payer,&ctx.accounts.sol_vault,&ctx.accounts.system_program)?;
+        }
 
-     
+        if seed > 0 {
+            let previous_lottery=ctx.accounts.previous_lottery.as_ref().ok_or(ErrorCode::IncorrectPreviousRound)?;
+            let previous_sol_vault=if previous_lottery.pays_in_tokens() {
+                let previous_vault=ctx.accounts.previous_vault.as_ref().ok_or(ErrorCode::MissingPaymentAccounts)?;
+                let expected_vault=Pubkey::find_program_address(&[b"vault".as_ref(),previous_lottery.key().as_ref()],&crate::ID).0;
+                require!(previous_vault.key()==expected_vault,ErrorCode::IncorrectPreviousRound);
+                //token payouts never touch the sol vault 
+                &ctx.accounts.sol_vault
+            } else {
+                let previous_sol_vault=ctx.accounts.previous_sol_vault.as_ref().ok_or(ErrorCode::MissingPaymentAccounts)?;
+                let expected_sol_vault=Pubkey::create_program_address(
+                    &[b"sol_vault".as_ref(),previous_lottery.key().as_ref(),&[previous_lottery.sol_vault_bump]],
+                    &crate::ID,
+                ).map_err(|_| ErrorCode::IncorrectPreviousRound)?;
+                require!(previous_sol_vault.key()==expected_sol_vault,ErrorCode::IncorrectPreviousRound);
+                previous_sol_vault
+            };
+
+            pay_out(
+                previous_lottery,
+                &ctx.accounts.sol_vault.to_account_info(),
+                previous_sol_vault,
+                &ctx.accounts.system_program,
+                &ctx.accounts.payment_mint,
+                &ctx.accounts.vault,
+                &ctx.accounts.previous_vault,
+                &ctx.accounts.payment_token_program,
+                seed,
+            )?;
+        }
 
-    #[account(
-        mut,
-        seeds=[b"collection_mint".as_ref()],
-        bump
-    )]
-    pub collection_mint:InterfaceAccount<'info,Mint> ,
+        let round_template=&mut ctx.accounts.round_template;
+        round_template.round=round;
+        round_template.current_lottery=ctx.accounts.token_lottery.key();
 
+        emit!(RoundStarted{
+            round_template:round_template.key(),
+            lottery:round_template.current_lottery,
+            round,
+            seed_jackpot:seed,
+        });
 
-    pub associated_token_program:Program<'info,AssociatedToken>,
-    pub token_program:Interface<'info,TokenInterface>,
-    pub system_program:Program<'info,System>,
-    pub token_metadata_program:Program<'info,Metadata> ,
-    pub rent:Sysvar<'info,Rent>
+        Ok(())
+    }
 
-}
+    //permissionless ,unclaimed tiers go back to the commit phase and choose_winner draws them again 
+    pub fn request_redraw(ctx:Context<InitializeRequestRedraw>)->Result<()> {
+        let clock=Clock::get()?;
+        let token_lottery=&mut ctx.accounts.token_lottery;
+        require!(!token_lottery.paused,ErrorCode::LotteryPaused);
+        token_lottery.require_phase(LotteryPhase::Drawn)?;
+        require!(clock.slot > token_lottery.claim_deadline,ErrorCode::ClaimDeadlineNotReached);
+        require!(token_lottery.can_redraw(),ErrorCode::RedrawNotAvailable);
+
+        token_lottery.redraws+=1;
+        token_lottery.randomness_account=Pubkey::default();
+        //the recommit budget and the barred committers carry over ,a redraw is no fresh chance to re-roll 
+        //if nobody commits or draws within another claim window ,rollover_unclaimed takes the pot instead 
+        token_lottery.claim_deadline=clock.slot.checked_add(token_lottery.claim_window_slots).ok_or(ErrorCode::MathOverflow)?;
+        token_lottery.phase=LotteryPhase::SalesClosed;
+
+        emit!(RedrawRequested{
+            lottery:token_lottery.key(),
+            redraw:token_lottery.redraws,
+            unclaimed_tiers:token_lottery.unclaimed_tiers() as u8,
+        });
 
-#[derive(Accounts)]
- pub struct InitializeChooseWinner<'info>{
-    #[account(
-        mut
-    )]
-    pub payer:Signer<'info>,
-     
-    #[account(
-        mut,
-       seeds=[b"token_lottery".as_ref()],
-       bump=token_lottery.bump
-    )]
-    pub token_lottery:Account<'info,TokenLottery> ,
-  /// CHECK: This account will be initialized by the metaplex program
-    pub randomness_account_data:UncheckedAccount<'info>,
+        Ok(())
+    }
 
-    pub system_program:Program<'info,System>
- }
+    //the next round usually exists only after this one drew ,so the destination can change until the rollover 
+    pub fn set_rollover_lottery(ctx:Context<InitializeSetRolloverLottery>,rollover_lottery:Pubkey)->Result<()> {
+        let token_lottery=&mut ctx.accounts.token_lottery;
+        require!(ctx.accounts.payer.key()==token_lottery.authority,ErrorCode::NotAuthorized);
+        require!(!matches!(token_lottery.phase,LotteryPhase::Settled | LotteryPhase::Cancelled),ErrorCode::InvalidLotteryPhase);
+        require!(rollover_lottery != token_lottery.key(),ErrorCode::IncorrectRolloverDestination);
 
- #[derive(Accounts)]
-pub struct InitializeCommitWinner<'info>{
-    #[account(mut)]
-    pub payer:Signer<'info> ,
+        token_lottery.rollover_lottery=rollover_lottery;
 
-     #[account(
-        mut,
-        seeds=[b"token_lottery".as_ref()],
-        bump=token_lottery.bump
+        emit!(RolloverLotterySet{
+            lottery:token_lottery.key(),
+            rollover_lottery,
+        });
+
+        Ok(())
+    }
+
+    //permissionless once the claim deadline passed ,whatever is left of the pot grows the next round 
+    //or goes to the treasurer when no next lottery is set ,a redraw that stalled before its draw falls back here too 
+    pub fn rollover_unclaimed(ctx:Context<InitializeRolloverUnclaimed>)->Result<()> {
+        let clock=Clock::get()?;
+        let token_lottery=&mut ctx.accounts.token_lottery;
+        require!(!token_lottery.paused,ErrorCode::LotteryPaused);
+        let stalled_redraw=token_lottery.redraws > 0 &&
+            matches!(token_lottery.phase,LotteryPhase::SalesClosed | LotteryPhase::RandomnessCommitted);
+        if !stalled_redraw {
+            token_lottery.require_phase(LotteryPhase::Drawn)?;
+        }
+        require!(clock.slot > token_lottery.claim_deadline,ErrorCode::ClaimDeadlineNotReached);
+        require!(stalled_redraw || !token_lottery.can_redraw(),ErrorCode::RedrawPending);
+
+        //a jackpot nobody claimed was never paid ,it goes back to the jackpot instead of the next round 
+        let jackpot_return=if token_lottery.tier_claimed.first()==Some(&false) {
+            token_lottery.jackpot_prize.min(token_lottery.token_lottery_pot)
+        } else {
+            0
+        };
+        if jackpot_return > 0 {
+            token_lottery.jackpot_prize=0;
+            token_lottery.token_lottery_pot-=jackpot_return;
+
+            let jackpot=ctx.accounts.jackpot.as_mut().ok_or(ErrorCode::MissingJackpotAccount)?;
+            pay_out(
+                &ctx.accounts.token_lottery,
+                &jackpot.to_account_info(),
+                &ctx.accounts.sol_vault,
+                &ctx.accounts.system_program,
+                &ctx.accounts.payment_mint,
+                &ctx.accounts.jackpot_vault,
+                &ctx.accounts.vault,
+                &ctx.accounts.payment_token_program,
+                jackpot_return,
+            )?;
+            jackpot.balance=jackpot.balance.checked_add(jackpot_return).ok_or(ErrorCode::MathOverflow)?;
+            jackpot.total_paid=jackpot.total_paid.saturating_sub(jackpot_return);
+
+            emit!(JackpotReturned{
+                jackpot:jackpot.key(),
+                lottery:ctx.accounts.token_lottery.key(),
+                amount:jackpot_return,
+            });
+        }
+
+        let token_lottery=&mut ctx.accounts.token_lottery;
+        let amount=token_lottery.token_lottery_pot;
+        token_lottery.token_lottery_pot=0;
+        token_lottery.phase=LotteryPhase::Settled;
+
+        let mut payout=amount;
+        let destination=match ctx.accounts.next_lottery.as_mut() {
+            Some(next_lottery)=>{
+                require!(next_lottery.payment_mint==token_lottery.payment_mint,ErrorCode::IncorrectRolloverDestination);
+                require!(matches!(next_lottery.phase,LotteryPhase::Created | LotteryPhase::Open),ErrorCode::InvalidLotteryPhase);
+                if token_lottery.pays_in_tokens() {
+                    let next_vault=Pubkey::find_program_address(&[b"vault".as_ref(),next_lottery.key().as_ref()],&crate::ID).0;
+                    let destination_token_account=ctx.accounts.destination_token_account.as_ref().ok_or(ErrorCode::MissingPaymentAccounts)?;
+                    require!(destination_token_account.key()==next_vault,ErrorCode::IncorrectRolloverDestination);
+                } else {
+                    let next_sol_vault=Pubkey::create_program_address(
+                        &[b"sol_vault".as_ref(),next_lottery.key().as_ref(),&[next_lottery.sol_vault_bump]],
+                        &crate::ID,
+                    ).map_err(|_| ErrorCode::IncorrectRolloverDestination)?;
+                    require!(ctx.accounts.destination.key()==next_sol_vault,ErrorCode::IncorrectRolloverDestination);
+                }
+                next_lottery.token_lottery_pot=next_lottery.token_lottery_pot.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+                next_lottery.seed_jackpot=next_lottery.seed_jackpot.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+                next_lottery.key()
+            },
+            //template rounds keep the pot ,start_next_round carries it into the next round as its seed jackpot 
+            None if token_lottery.round_template != Pubkey::default() && token_lottery.rollover_lottery==Pubkey::default()=>{
+                token_lottery.token_lottery_pot=amount;
+                payout=0;
+                token_lottery.round_template
+            },
+            None=>{
+                require!(token_lottery.rollover_lottery==Pubkey::default(),ErrorCode::IncorrectRolloverDestination);
+                if token_lottery.pays_in_tokens() {
+                    let destination_token_account=ctx.accounts.destination_token_account.as_ref().ok_or(ErrorCode::MissingPaymentAccounts)?;
+                    require!(destination_token_account.owner==token_lottery.treasurer,ErrorCode::IncorrectRolloverDestination);
+                } else {
+                    require!(ctx.accounts.destination.key()==token_lottery.treasurer,ErrorCode::IncorrectRolloverDestination);
+                }
+                token_lottery.treasurer
+            },
+        };
+
+        if payout > 0 {
+            pay_out(
+                &ctx.accounts.token_lottery,
+                &ctx.accounts.destination.to_account_info(),
+                &ctx.accounts.sol_vault,
+                &ctx.accounts.system_program,
+                &ctx.accounts.payment_mint,
+                &ctx.accounts.destination_token_account,
+                &ctx.accounts.vault,
+                &ctx.accounts.payment_token_program,
+                payout,
+            )?;
+        }
+
+        emit!(UnclaimedRolledOver{
+            lottery:ctx.accounts.token_lottery.key(),
+            destination,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    //incident switch ,halts ticket sales ,prize claims ,redraws and rollovers until unpaused 
+    pub fn pause(ctx:Context<InitializePause>)->Result<()> {
+        let token_lottery=&mut ctx.accounts.token_lottery;
+        require!(token_lottery.can_pause(ctx.accounts.payer.key()),ErrorCode::NotAuthorized);
+
+        if !token_lottery.paused {
+            token_lottery.paused=true;
+            token_lottery.paused_at=Clock::get()?.slot;
+        }
+        msg!("Lottery {} paused",token_lottery.lottery_id);
+
+        emit!(LotteryPaused{
+            lottery:token_lottery.key(),
+            by:ctx.accounts.payer.key(),
+            slot:token_lottery.paused_at,
+        });
+
+        Ok(())
+    }
+
+    pub fn unpause(ctx:Context<InitializeUnpause>)->Result<()> {
+        let token_lottery=&mut ctx.accounts.token_lottery;
+        require!(token_lottery.can_pause(ctx.accounts.payer.key()),ErrorCode::NotAuthorized);
+
+        if token_lottery.paused {
+            token_lottery.extend_claim_deadline(Clock::get()?.slot)?;
+        }
+        token_lottery.paused=false;
+        msg!("Lottery {} unpaused",token_lottery.lottery_id);
+
+        emit!(LotteryUnpaused{
+            lottery:token_lottery.key(),
+            by:ctx.accounts.payer.key(),
+            claim_deadline:token_lottery.claim_deadline,
+        });
+
+        Ok(())
+    }
+
+    //house fees become withdrawable once the draw happened ,before that they may still be refunded 
+    pub fn withdraw_fees(ctx:Context<InitializeWithdrawFees>)->Result<()> {
+        let token_lottery=&mut ctx.accounts.token_lottery;
+        require!(ctx.accounts.payer.key()==token_lottery.treasurer,ErrorCode::NotAuthorized);
+        require!(matches!(token_lottery.phase,LotteryPhase::Drawn | LotteryPhase::Settled),ErrorCode::InvalidLotteryPhase);
+
+        let amount=token_lottery.fees_accrued;
+        require!(amount > 0,ErrorCode::NoFeesAccrued);
+        token_lottery.fees_accrued=0;
+
+        pay_out(
+            &ctx.accounts.token_lottery,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.sol_vault,
+            &ctx.accounts.system_program,
+            &ctx.accounts.payment_mint,
+            &ctx.accounts.payer_token_account,
+            &ctx.accounts.vault,
+            &ctx.accounts.payment_token_program,
+            amount,
+        )?;
+
+        emit!(FeesWithdrawn{
+            lottery:ctx.accounts.token_lottery.key(),
+            treasurer:ctx.accounts.payer.key(),
+            amount,
+        });
+
+        Ok(())
+    }
+
+    //one jackpot per creator and payment mint ,every lottery of that creator (or every round of a template) feeds it 
+    pub fn create_jackpot(ctx:Context<InitializeCreateJackpot>)->Result<()> {
+        let token_lottery=&ctx.accounts.token_lottery;
+        if token_lottery.pays_in_tokens() {
+            require!(ctx.accounts.jackpot_vault.is_some(),ErrorCode::MissingPaymentAccounts);
+        }
+
+        let jackpot=&mut ctx.accounts.jackpot;
+        jackpot.creator=token_lottery.creator;
+        jackpot.payment_mint=token_lottery.payment_mint;
+        jackpot.balance=0;
+        jackpot.total_paid=0;
+        jackpot.bump=ctx.bumps.jackpot;
+
+        emit!(JackpotCreated{
+            jackpot:jackpot.key(),
+            creator:jackpot.creator,
+            payment_mint:jackpot.payment_mint,
+        });
+
+        Ok(())
+    }
+
+    //a referrer registers once per lottery and shares its key ,buyers pass the referral account to buy_ticket 
+    pub fn register_referrer(ctx:Context<InitializeRegisterReferrer>)->Result<()> {
+        let referral=&mut ctx.accounts.referral;
+        referral.lottery=ctx.accounts.token_lottery.key();
+        referral.referrer=ctx.accounts.payer.key();
+        referral.claimable=0;
+        referral.total_earned=0;
+        referral.bump=ctx.bumps.referral;
+
+        emit!(ReferrerRegistered{
+            lottery:referral.lottery,
+            referrer:referral.referrer,
+            referral:referral.key(),
+        });
+
+        Ok(())
+    }
+
+    //referral rewards are paid like fees ,only once the lottery drew 
+    pub fn claim_referral_rewards(ctx:Context<InitializeClaimReferralRewards>)->Result<()> {
+        require!(
+            matches!(ctx.accounts.token_lottery.phase,LotteryPhase::Drawn | LotteryPhase::Settled),
+            ErrorCode::InvalidLotteryPhase
+        );
+
+        let amount=ctx.accounts.referral.claimable;
+        require!(amount > 0,ErrorCode::NoReferralRewards);
+        ctx.accounts.referral.claimable=0;
+        ctx.accounts.token_lottery.referral_rewards=ctx.accounts.token_lottery.referral_rewards
+            .checked_sub(amount).ok_or(ErrorCode::MathOverflow)?;
+
+        pay_out(
+            &ctx.accounts.token_lottery,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.sol_vault,
+            &ctx.accounts.system_program,
+            &ctx.accounts.payment_mint,
+            &ctx.accounts.payer_token_account,
+            &ctx.accounts.vault,
+            &ctx.accounts.payment_token_program,
+            amount,
+        )?;
+
+        emit!(ReferralRewardsClaimed{
+            lottery:ctx.accounts.token_lottery.key(),
+            referrer:ctx.accounts.payer.key(),
+            amount,
+        });
+
+        Ok(())
+    }
+
+    //permissionless settlement of the beneficiary waterfall ,remaining accounts are the beneficiaries in
+    //configured order ,their wallets for lamport lotteries or their payment mint token accounts otherwise 
+    pub fn distribute_proceeds<'info>(ctx:Context<'_,'_,'info,'info,InitializeDistributeProceeds<'info>>)->Result<()> {
+        let token_lottery=&ctx.accounts.token_lottery;
+        require!(matches!(token_lottery.phase,LotteryPhase::Drawn | LotteryPhase::Settled),ErrorCode::InvalidLotteryPhase);
+        require!(token_lottery.beneficiary_proceeds > 0,ErrorCode::NoProceeds);
+        require!(ctx.remaining_accounts.len()==token_lottery.beneficiaries.len(),ErrorCode::IncorrectBeneficiary);
+
+        let amounts=token_lottery.beneficiary_amounts();
+        for ((beneficiary,amount),account) in token_lottery.beneficiaries.iter().zip(amounts.iter()).zip(ctx.remaining_accounts.iter()) {
+            if *amount==0 {
+                continue;
+            }
+
+            let beneficiary_token_account=if token_lottery.pays_in_tokens() {
+                let token_account=InterfaceAccount::<TokenAccount>::try_from(account)?;
+                require!(
+                    token_account.owner==beneficiary.wallet && token_account.mint==token_lottery.payment_mint,
+                    ErrorCode::IncorrectBeneficiary
+                );
+                Some(Box::new(token_account))
+            } else {
+                require!(account.key()==beneficiary.wallet,ErrorCode::IncorrectBeneficiary);
+                None
+            };
+
+            pay_out(
+                token_lottery,
+                account,
+                &ctx.accounts.sol_vault,
+                &ctx.accounts.system_program,
+                &ctx.accounts.payment_mint,
+                &beneficiary_token_account,
+                &ctx.accounts.vault,
+                &ctx.accounts.payment_token_program,
+                *amount,
+            )?;
+        }
+
+        let token_lottery=&mut ctx.accounts.token_lottery;
+        token_lottery.beneficiary_proceeds=0;
+
+        emit!(ProceedsDistributed{
+            lottery:token_lottery.key(),
+            beneficiaries:token_lottery.beneficiaries.iter().map(|beneficiary| beneficiary.wallet).collect(),
+            amounts,
+        });
+
+        Ok(())
+    }
+
+    //anything can change before the first ticket ,after that lottery_end can only be extended 
+    pub fn update_config(ctx:Context<InitializeUpdateConfig>,update:ConfigUpdate)->Result<()> {
+        let clock=Clock::get()?;
+        let token_lottery=&mut ctx.accounts.token_lottery;
+        require!(ctx.accounts.payer.key()==token_lottery.authority,ErrorCode::NotAuthorized);
+        //sales that already ended stay closed ,neither the phase nor a later end can reopen them 
+        token_lottery.close_sales_if_ended(clock.slot);
+        require!(matches!(token_lottery.phase,LotteryPhase::Created | LotteryPhase::Open),ErrorCode::InvalidLotteryPhase);
+        if update.end.is_some() {
+            require!(clock.slot <= token_lottery.lottery_end,ErrorCode::ConfigFrozen);
+        }
+
+        let previous=token_lottery.config_values();
+
+        if token_lottery.ticket_number > 0 {
+            require!(
+                update.price.is_none() && update.start.is_none() && update.min_tickets.is_none() &&
+                update.max_tickets.is_none() && update.max_tickets_per_wallet.is_none() &&
+                update.randomness_queue.is_none() && update.reveal_timeout_slots.is_none() &&
+                update.crank_reward_bps.is_none() && update.fee_bps.is_none() && update.referral_bps.is_none() &&
+                update.claim_window_slots.is_none() && update.max_redraws.is_none() &&
+                update.jackpot_bps.is_none() && update.jackpot_odds.is_none(),
+                ErrorCode::ConfigFrozen
+            );
+            if let Some(end)=update.end {
+                require!(end >= token_lottery.lottery_end,ErrorCode::ConfigFrozen);
+            }
+        }
+
+        if let Some(price)=update.price {
+            token_lottery.price=price;
+        }
+        if let Some(start)=update.start {
+            token_lottery.lottery_start=start;
+        }
+        if let Some(end)=update.end {
+            token_lottery.lottery_end=end;
+        }
+        if let Some(min_tickets)=update.min_tickets {
+            token_lottery.min_tickets=min_tickets;
+        }
+        if let Some(max_tickets)=update.max_tickets {
+            token_lottery.max_tickets=max_tickets;
+        }
+        if let Some(max_tickets_per_wallet)=update.max_tickets_per_wallet {
+            token_lottery.max_tickets_per_wallet=max_tickets_per_wallet;
+        }
+        if let Some(randomness_queue)=update.randomness_queue {
+            token_lottery.randomness_queue=randomness_queue;
+        }
+        if let Some(reveal_timeout_slots)=update.reveal_timeout_slots {
+            require!(reveal_timeout_slots > 0,ErrorCode::InvalidRevealTimeout);
+            token_lottery.reveal_timeout_slots=reveal_timeout_slots;
+        }
+        //opening the draw up stays possible once tickets sold so a stalled operator can be routed around 
+        if let Some(permissionless_draw)=update.permissionless_draw {
+            token_lottery.permissionless_draw=permissionless_draw;
+        }
+        if let Some(crank_reward_bps)=update.crank_reward_bps {
+            require!(crank_reward_bps <= MAX_CRANK_REWARD_BPS,ErrorCode::InvalidCrankReward);
+            token_lottery.crank_reward_bps=crank_reward_bps;
+        }
+        if let Some(fee_bps)=update.fee_bps {
+            token_lottery.fee_bps=fee_bps;
+        }
+        if let Some(referral_bps)=update.referral_bps {
+            token_lottery.referral_bps=referral_bps;
+        }
+        if let Some(claim_window_slots)=update.claim_window_slots {
+            require!(claim_window_slots > 0,ErrorCode::InvalidClaimWindow);
+            token_lottery.claim_window_slots=claim_window_slots;
+        }
+        if let Some(max_redraws)=update.max_redraws {
+            require!(max_redraws <= MAX_REDRAWS,ErrorCode::InvalidRedraws);
+            token_lottery.max_redraws=max_redraws;
+        }
+        if let Some(jackpot_bps)=update.jackpot_bps {
+            token_lottery.jackpot_bps=jackpot_bps;
+        }
+        if let Some(jackpot_odds)=update.jackpot_odds {
+            token_lottery.jackpot_odds=jackpot_odds;
+        }
+
+        require!(token_lottery.lottery_start <= token_lottery.lottery_end,ErrorCode::InvalidLotteryWindow);
+        require!(token_lottery.max_tickets==0 || token_lottery.min_tickets <= token_lottery.max_tickets,ErrorCode::InvalidTicketLimits);
+        token_lottery.check_waterfall()?;
+        require!(token_lottery.jackpot_bps==0 || token_lottery.jackpot_odds > 0,ErrorCode::InvalidJackpotOdds);
+
+        emit!(ConfigUpdated{
+            lottery:token_lottery.key(),
+            previous,
+            current:token_lottery.config_values(),
+        });
+
+        Ok(())
+    }
+
+
+}
+
+#[derive(Accounts)]   //attribute for the account instruction
+#[instruction(lottery_id:u64)]
+//accounts we setup config function 
+pub struct InitializeConfig<'info> {
+
+     #[account(mut)]
+     pub payer:Signer <'info>,   //we used signer means the accounts should sign the transaction
+     #[account(
+        init,       //we use the inti attribute to init the account  
+        payer=payer,      //this means that payer account will pay for the account instruction 
+        space=8+TokenLottery::INIT_SPACE,    //8 means resverd space for account discr    token lottery means that the additiona space for the 
+        seeds=[b"token_lottery".as_ref(),payer.key().as_ref(),lottery_id.to_le_bytes().as_ref()],    //one pda per creator and lottery id 
+        bump      //bump auto by anchor  
+     )]
+
+     pub token_lottery:Box<Account<'info,TokenLottery>> ,   //we created the heap   
+
+     //only for lotteries paid in an spl / token 2022 mint ,leave empty for lamports 
+     pub payment_mint:Option<Box<InterfaceAccount<'info,Mint>>>,
+
+     #[account(
+        init,
+        payer=payer,
+        seeds=[b"vault".as_ref(),token_lottery.key().as_ref()],
+        bump,
+        token::mint=payment_mint,
+        token::authority=token_lottery,    //the lottery pda signs every payout 
+        token::token_program=payment_token_program
+     )]
+     pub vault:Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+
+     //holds the lamport pot ,funded with its own rent reserve so the lottery account is never debited 
+     #[account(
+        mut,
+        seeds=[b"sol_vault".as_ref(),token_lottery.key().as_ref()],
+        bump
      )]
+     pub sol_vault:SystemAccount<'info>,
+
+     pub payment_token_program:Option<Interface<'info,TokenInterface>>,
+
+     pub system_program:Program<'info,System>
+}
+ 
+
+#[derive(Accounts)]
+pub struct InitializeLottery<'info> {
+    #[account(mut)]
+    pub payer:Signer<'info> ,
+
+    #[account(
+        mut,
+        seeds=[b"token_lottery".as_ref(),token_lottery.creator.as_ref(),token_lottery.lottery_id.to_le_bytes().as_ref()],
+        bump=token_lottery.bump
+    )]
+    pub token_lottery:Box<Account<'info,TokenLottery>>,
+
+   //this account is init as spl token mint  
+    #[account(
+     init,
+     payer=payer,
+     mint::decimals=0,   //the token will produce the token mint for th 0decimal space 
+     mint::authority=collection_mint,    //set to pda means giving the authority to the over mint and freeze 
+     mint::freeze_authority=collection_mint,
+    seeds=[b"collection_mint".as_ref(),token_lottery.key().as_ref()],
+    bump
+)]
+
+    pub collection_mint:Box<InterfaceAccount<'info,Mint>>,   
+
+  /// CHECK: This account will be initialized by the metaplex program
+    #[account(mut)]
+    pub metadata:UncheckedAccount<'info> ,
+  /// CHECK: This account will be initialized by the metaplex program
+    #[account(mut)] 
+    pub master_edition:UncheckedAccount<'info>,
+
+      #[account(
+        init_if_needed,   //only init if account does not init  
+        payer=payer,
+        seeds=[b"collection_token_account".as_ref(),token_lottery.key().as_ref()],
+        bump,
+        token::mint=collection_mint,   //which account we habe to mint  
+        token::authority=collection_token_account
+      )]
+
+    //this account holds the token minted by collection mint 
+     pub collection_token_account:Box<InterfaceAccount<'info,TokenAccount>>,
+
+
+
+    pub associated_token_program:Program<'info,AssociatedToken>,
+    pub token_metadata_program:Program<'info,Metadata>,
+    pub system_program:Program<'info,System>,
+    pub token_program:Interface<'info,TokenInterface>,
+    pub rent:Sysvar<'info,Rent>,
+
+
+}
+
+#[derive(Accounts)] 
+pub struct InitializeBuyTicket<'info> {
+    #[account(mut)] 
+    pub payer:Signer<'info> ,
+
+    #[account(mut,
+     seeds=[b"token_lottery".as_ref(),token_lottery.creator.as_ref(),token_lottery.lottery_id.to_le_bytes().as_ref()] ,
+     bump=token_lottery.bump
+    )]
+
+    pub token_lottery:Account<'info,TokenLottery> ,
+
+    //how many tickets this wallet bought in this lottery 
+    #[account(
+        init_if_needed,
+        payer=payer,
+        space=8+PurchaseRecord::INIT_SPACE,
+        seeds=[b"purchase_record".as_ref(),token_lottery.key().as_ref(),payer.key().as_ref()],
+        bump
+    )]
+    pub purchase_record:Box<Account<'info,PurchaseRecord>>,
+
+    //optional ,credits the referrer with referral_bps of this purchase 
+    #[account(
+        mut,
+        constraint=referral.lottery==token_lottery.key()@ErrorCode::IncorrectReferral,
+        constraint=referral.referrer!=payer.key()@ErrorCode::SelfReferral
+    )]
+    pub referral:Option<Box<Account<'info,Referral>>>,
+
+
+    #[account(
+        init,
+        payer=payer,
+        mint::decimals=0,
+        mint::authority=collection_mint,
+        mint::freeze_authority=collection_mint,
+        mint::token_program=token_program,
+        seeds=[token_lottery.key().as_ref(),token_lottery.ticket_number.to_le_bytes().as_ref()],
+        bump
+
+    )]
+    pub ticket_mint:InterfaceAccount<'info,Mint> ,
+
+    #[account(
+        init,
+        payer=payer,
+        associated_token::mint=ticket_mint,
+        associated_token::authority=payer,
+        associated_token::token_program=token_program
+    )]
+    pub destination:InterfaceAccount<'info,TokenAccount>,
+   
+
+
+      
+    /// CHECK: This account will be initialized by the metaplex program
+     #[account(mut,
+     seeds=[b"metadata",token_metadata_program.key().as_ref(),
+     ticket_mint.key().as_ref()],
+ 
+     seeds::program=token_metadata_program.key(),
+     bump,
+    )]
+     pub metadata:UncheckedAccount<'info> ,
+
+
+
+    /// CHECK: This account will be initialized by the metaplex program
+     #[account(mut,
+    seeds=[b"metadata",token_metadata_program.key().as_ref(),
+      ticket_mint.key().as_ref(),b"edition" ],
+      bump,
+     seeds::program=token_metadata_program.key()
+)] 
+     pub master_edition:UncheckedAccount<'info>,
+     
+      /// CHECK: This account will be initialized by the metaplex program   
+      #[account(mut,
+       seeds=[b"metadata",token_metadata_program.key().as_ref(),collection_mint.key().as_ref()],
+       bump,
+       seeds::program=token_metadata_program.key()
+    )]
+      pub collection_metadata:UncheckedAccount<'info> ,
+
+        
+       /// CHECK: This account will be initialized by the metaplex program
+       #[account(mut,
+       seeds=[b"metadata",token_metadata_program.key().as_ref(),
+       collection_mint.key().as_ref(),b"edition"],
+       bump,
+       seeds::program=token_metadata_program.key(),
+    )]
+
+     pub collection_master_edition:UncheckedAccount<'info>,
+
+
+
+     
+
+    #[account(
+        mut,
+        seeds=[b"collection_mint".as_ref(),token_lottery.key().as_ref()],
+        bump
+    )]
+    pub collection_mint:InterfaceAccount<'info,Mint> ,
+
+    #[account(address=token_lottery.payment_mint)]
+    pub payment_mint:Option<Box<InterfaceAccount<'info,Mint>>>,
+
+    #[account(
+        mut,
+        token::mint=payment_mint,
+        token::authority=payer,
+        token::token_program=payment_token_program
+    )]
+    pub payer_token_account:Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds=[b"vault".as_ref(),token_lottery.key().as_ref()],
+        bump,
+        token::token_program=payment_token_program
+    )]
+    pub vault:Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds=[b"sol_vault".as_ref(),token_lottery.key().as_ref()],
+        bump=token_lottery.sol_vault_bump
+    )]
+    pub sol_vault:SystemAccount<'info>,
+
+    pub payment_token_program:Option<Interface<'info,TokenInterface>>,
+
+
+    pub associated_token_program:Program<'info,AssociatedToken>,
+    pub token_program:Interface<'info,TokenInterface>,
+    pub system_program:Program<'info,System>,
+    pub token_metadata_program:Program<'info,Metadata> ,
+    pub rent:Sysvar<'info,Rent>
+
+}
+
+#[derive(Accounts)]
+ pub struct InitializeChooseWinner<'info>{
+    #[account(
+        mut
+    )]
+    pub payer:Signer<'info>,
+     
+    #[account(
+        mut,
+       seeds=[b"token_lottery".as_ref(),token_lottery.creator.as_ref(),token_lottery.lottery_id.to_le_bytes().as_ref()],
+       bump=token_lottery.bump
+    )]
+    pub token_lottery:Account<'info,TokenLottery> ,
+  /// CHECK: owner checked here ,the data is parsed as switchboard randomness in the instruction
+    #[account(
+        constraint=is_switchboard_account(randomness_account_data.owner)@ErrorCode::InvalidRandomnessAccount
+    )]
+    pub randomness_account_data:UncheckedAccount<'info>,
+
+    //required when the lottery feeds a progressive jackpot 
+    #[account(
+        mut,
+        seeds=[b"jackpot".as_ref(),token_lottery.creator.as_ref(),token_lottery.payment_mint.as_ref()],
+        bump=jackpot.bump
+    )]
+    pub jackpot:Option<Box<Account<'info,Jackpot>>>,
+
+    #[account(
+        mut,
+        seeds=[b"jackpot_vault".as_ref(),token_lottery.creator.as_ref(),token_lottery.payment_mint.as_ref()],
+        bump,
+        token::token_program=payment_token_program
+    )]
+    pub jackpot_vault:Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+
+    #[account(address=token_lottery.payment_mint)]
+    pub payment_mint:Option<Box<InterfaceAccount<'info,Mint>>>,
+
+    #[account(
+        mut,
+        token::mint=payment_mint,
+        token::authority=payer,
+        token::token_program=payment_token_program
+    )]
+    pub payer_token_account:Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds=[b"vault".as_ref(),token_lottery.key().as_ref()],
+        bump,
+        token::token_program=payment_token_program
+    )]
+    pub vault:Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds=[b"sol_vault".as_ref(),token_lottery.key().as_ref()],
+        bump=token_lottery.sol_vault_bump
+    )]
+    pub sol_vault:SystemAccount<'info>,
+
+    pub payment_token_program:Option<Interface<'info,TokenInterface>>,
+
+    pub system_program:Program<'info,System>
+ }
+
+ #[derive(Accounts)]
+pub struct InitializeCommitWinner<'info>{
+    #[account(mut)]
+    pub payer:Signer<'info> ,
+
+     #[account(
+        mut,
+        seeds=[b"token_lottery".as_ref(),token_lottery.creator.as_ref(),token_lottery.lottery_id.to_le_bytes().as_ref()],
+        bump=token_lottery.bump
+     )]
+
+    pub token_lottery:Account<'info,TokenLottery>,
+   
+   /// CHECK: owner checked here ,the data is parsed as switchboard randomness in the instruction
+    #[account(
+        constraint=is_switchboard_account(randomness_account_data.owner)@ErrorCode::InvalidRandomnessAccount
+    )]
+    pub randomness_account_data:UncheckedAccount<'info>,
+  
+    pub system_program:Program<'info,System>
+}
+
+#[derive(Accounts)]
+pub struct InitializeRecommitRandomness<'info>{
+    pub payer:Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"token_lottery".as_ref(),token_lottery.creator.as_ref(),token_lottery.lottery_id.to_le_bytes().as_ref()],
+        bump=token_lottery.bump
+    )]
+    pub token_lottery:Account<'info,TokenLottery>,
+
+    /// CHECK: must be the currently committed randomness account ,checked in the instruction
+    #[account(
+        constraint=is_switchboard_account(abandoned_randomness_account_data.owner)@ErrorCode::InvalidRandomnessAccount
+    )]
+    pub abandoned_randomness_account_data:UncheckedAccount<'info>,
+
+    /// CHECK: owner checked here ,the data is parsed as switchboard randomness in the instruction
+    #[account(
+        constraint=is_switchboard_account(randomness_account_data.owner)@ErrorCode::InvalidRandomnessAccount
+    )]
+    pub randomness_account_data:UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(tier:u8)]
+pub struct InitializeClaimPrize<'info>{
+   #[account(mut)]
+   pub payer:Signer<'info> ,
+    
+
+   #[account(
+    mut,
+    seeds=[b"token_lottery".as_ref(),token_lottery.creator.as_ref(),token_lottery.lottery_id.to_le_bytes().as_ref()],
+    bump=token_lottery.bump
+ )]
+
+pub token_lottery:Account<'info,TokenLottery>, 
+
+#[account(
+    mut,
+    seeds=[b"collection_mint".as_ref(),token_lottery.key().as_ref()],
+    bump
+)]
+
+pub collection_mint:InterfaceAccount<'info,Mint> ,
+
+
+#[account(
+    seeds=[b"metadata",token_metadata_program.key().as_ref(),ticket_mint.key().as_ref()],
+    bump,
+    seeds::program=token_metadata_program.key(),
+)]
+
+pub metadata:Account<'info,MetadataAccount> ,
+
+#[account(
+    seeds=[token_lottery.key().as_ref(),token_lottery.winning_ticket(tier).to_le_bytes().as_ref()],
+    bump
+)]
+pub ticket_mint:InterfaceAccount<'info,Mint> ,
+ 
+ #[account(
+    associated_token::mint=ticket_mint,
+    associated_token::authority=payer,
+    associated_token::token_program=token_program,
+ )]
+
+pub destination:InterfaceAccount<'info,TokenAccount>,
+
+#[account(
+    mut,
+    seeds=[b"metadata",token_metadata_program.key().as_ref(),collection_mint.key().as_ref()],
+    bump,
+    seeds::program=token_metadata_program.key()
+)]
+
+ 
+pub collection_metadata:Account<'info,MetadataAccount>,
+
+    #[account(address=token_lottery.payment_mint)]
+    pub payment_mint:Option<Box<InterfaceAccount<'info,Mint>>>,
+
+    #[account(
+        mut,
+        token::mint=payment_mint,
+        token::authority=payer,
+        token::token_program=payment_token_program
+    )]
+    pub payer_token_account:Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds=[b"vault".as_ref(),token_lottery.key().as_ref()],
+        bump,
+        token::token_program=payment_token_program
+    )]
+    pub vault:Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds=[b"sol_vault".as_ref(),token_lottery.key().as_ref()],
+        bump=token_lottery.sol_vault_bump
+    )]
+    pub sol_vault:SystemAccount<'info>,
+
+    pub payment_token_program:Option<Interface<'info,TokenInterface>>,
+
+pub token_program:Interface<'info,TokenInterface>,
+pub token_metadata_program:Program<'info,Metadata>,
+pub system_program:Program<'info,System> ,
+  
+
+}
+
+#[derive(Accounts)]
+pub struct InitializeCancelLottery<'info>{
+    pub payer:Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"token_lottery".as_ref(),token_lottery.creator.as_ref(),token_lottery.lottery_id.to_le_bytes().as_ref()],
+        bump=token_lottery.bump
+    )]
+    pub token_lottery:Account<'info,TokenLottery>,
+
+    /// CHECK: only needed while randomness is committed ,must be the committed account
+    #[account(
+        constraint=is_switchboard_account(randomness_account_data.owner)@ErrorCode::InvalidRandomnessAccount
+    )]
+    pub randomness_account_data:Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(ticket_number:u64)]
+pub struct InitializeRefundTicket<'info>{
+    #[account(mut)]
+    pub payer:Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"token_lottery".as_ref(),token_lottery.creator.as_ref(),token_lottery.lottery_id.to_le_bytes().as_ref()],
+        bump=token_lottery.bump
+    )]
+    pub token_lottery:Box<Account<'info,TokenLottery>>,
+
+    #[account(
+        seeds=[b"collection_mint".as_ref(),token_lottery.key().as_ref()],
+        bump
+    )]
+    pub collection_mint:Box<InterfaceAccount<'info,Mint>>,
+
+    #[account(
+        seeds=[token_lottery.key().as_ref(),ticket_number.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub ticket_mint:Box<InterfaceAccount<'info,Mint>>,
+
+    #[account(
+        seeds=[b"metadata",token_metadata_program.key().as_ref(),ticket_mint.key().as_ref()],
+        bump,
+        seeds::program=token_metadata_program.key(),
+    )]
+    pub metadata:Box<Account<'info,MetadataAccount>>,
+
+    #[account(
+        associated_token::mint=ticket_mint,
+        associated_token::authority=payer,
+        associated_token::token_program=token_program,
+    )]
+    pub destination:Box<InterfaceAccount<'info,TokenAccount>>,
+
+    //fails with account already in use when the ticket was refunded before 
+    #[account(
+        init,
+        payer=payer,
+        space=8+TicketRefund::INIT_SPACE,
+        seeds=[b"ticket_refund".as_ref(),ticket_mint.key().as_ref()],
+        bump
+    )]
+    pub ticket_refund:Account<'info,TicketRefund>,
+
+    #[account(address=token_lottery.payment_mint)]
+    pub payment_mint:Option<Box<InterfaceAccount<'info,Mint>>>,
+
+    #[account(
+        mut,
+        token::mint=payment_mint,
+        token::authority=payer,
+        token::token_program=payment_token_program
+    )]
+    pub payer_token_account:Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds=[b"vault".as_ref(),token_lottery.key().as_ref()],
+        bump,
+        token::token_program=payment_token_program
+    )]
+    pub vault:Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds=[b"sol_vault".as_ref(),token_lottery.key().as_ref()],
+        bump=token_lottery.sol_vault_bump
+    )]
+    pub sol_vault:SystemAccount<'info>,
+
+    pub payment_token_program:Option<Interface<'info,TokenInterface>>,
+
+    pub token_program:Interface<'info,TokenInterface>,
+    pub token_metadata_program:Program<'info,Metadata>,
+    pub system_program:Program<'info,System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeUpdateCollectionMetadata<'info>{
+    pub payer:Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"token_lottery".as_ref(),token_lottery.creator.as_ref(),token_lottery.lottery_id.to_le_bytes().as_ref()],
+        bump=token_lottery.bump
+    )]
+    pub token_lottery:Box<Account<'info,TokenLottery>>,
+
+    #[account(
+        seeds=[b"collection_mint".as_ref(),token_lottery.key().as_ref()],
+        bump
+    )]
+    pub collection_mint:Box<InterfaceAccount<'info,Mint>>,
+
+    /// CHECK: This account is updated by the metaplex program
+    #[account(
+        mut,
+        seeds=[b"metadata",token_metadata_program.key().as_ref(),collection_mint.key().as_ref()],
+        bump,
+        seeds::program=token_metadata_program.key()
+    )]
+    pub metadata:UncheckedAccount<'info>,
+
+    pub token_metadata_program:Program<'info,Metadata>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeProposeAuthority<'info>{
+    pub payer:Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"token_lottery".as_ref(),token_lottery.creator.as_ref(),token_lottery.lottery_id.to_le_bytes().as_ref()],
+        bump=token_lottery.bump
+    )]
+    pub token_lottery:Account<'info,TokenLottery>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeAcceptAuthority<'info>{
+    pub payer:Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"token_lottery".as_ref(),token_lottery.creator.as_ref(),token_lottery.lottery_id.to_le_bytes().as_ref()],
+        bump=token_lottery.bump
+    )]
+    pub token_lottery:Account<'info,TokenLottery>,
+}
+
+#[derive(Accounts)]
+#[instruction(template_id:u64)]
+pub struct InitializeCreateRoundTemplate<'info>{
+    #[account(mut)]
+    pub payer:Signer<'info>,
+
+    #[account(
+        init,
+        payer=payer,
+        space=8+RoundTemplate::INIT_SPACE,
+        seeds=[b"round_template".as_ref(),payer.key().as_ref(),template_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub round_template:Box<Account<'info,RoundTemplate>>,
+
+    //only for rounds paid in an spl / token 2022 mint ,leave empty for lamports 
+    pub payment_mint:Option<Box<InterfaceAccount<'info,Mint>>>,
+
+    pub system_program:Program<'info,System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeStartNextRound<'info>{
+    #[account(mut)]
+    pub payer:Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"round_template".as_ref(),round_template.authority.as_ref(),round_template.template_id.to_le_bytes().as_ref()],
+        bump=round_template.bump
+    )]
+    pub round_template:Box<Account<'info,RoundTemplate>>,
+
+    //required from the second round on 
+    #[account(
+        mut,
+        address=round_template.current_lottery@ErrorCode::IncorrectPreviousRound
+    )]
+    pub previous_lottery:Option<Box<Account<'info,TokenLottery>>>,
+
+    //only needed when a lamport round left a pot to carry over ,checked in the instruction 
+    #[account(mut)]
+    pub previous_sol_vault:Option<SystemAccount<'info>>,
+
+    #[account(
+        mut,
+        token::mint=payment_mint,
+        token::token_program=payment_token_program
+    )]
+    pub previous_vault:Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+
+    #[account(
+        init,
+        payer=payer,
+        space=8+TokenLottery::INIT_SPACE,
+        seeds=[b"token_lottery".as_ref(),round_template.key().as_ref(),(round_template.round+1).to_le_bytes().as_ref()],
+        bump
+    )]
+    pub token_lottery:Box<Account<'info,TokenLottery>>,
+
+    #[account(address=round_template.payment_mint)]
+    pub payment_mint:Option<Box<InterfaceAccount<'info,Mint>>>,
+
+    #[account(
+        init,
+        payer=payer,
+        seeds=[b"vault".as_ref(),token_lottery.key().as_ref()],
+        bump,
+        token::mint=payment_mint,
+        token::authority=token_lottery,
+        token::token_program=payment_token_program
+    )]
+    pub vault:Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds=[b"sol_vault".as_ref(),token_lottery.key().as_ref()],
+        bump
+    )]
+    pub sol_vault:SystemAccount<'info>,
+
+    pub payment_token_program:Option<Interface<'info,TokenInterface>>,
+
+    pub system_program:Program<'info,System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeRequestRedraw<'info>{
+    pub payer:Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"token_lottery".as_ref(),token_lottery.creator.as_ref(),token_lottery.lottery_id.to_le_bytes().as_ref()],
+        bump=token_lottery.bump
+    )]
+    pub token_lottery:Account<'info,TokenLottery>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeSetRolloverLottery<'info>{
+    pub payer:Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"token_lottery".as_ref(),token_lottery.creator.as_ref(),token_lottery.lottery_id.to_le_bytes().as_ref()],
+        bump=token_lottery.bump
+    )]
+    pub token_lottery:Account<'info,TokenLottery>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeRolloverUnclaimed<'info>{
+    pub payer:Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"token_lottery".as_ref(),token_lottery.creator.as_ref(),token_lottery.lottery_id.to_le_bytes().as_ref()],
+        bump=token_lottery.bump
+    )]
+    pub token_lottery:Box<Account<'info,TokenLottery>>,
+
+    //required when rollover_lottery is set ,its pot is credited with the unclaimed amount 
+    #[account(
+        mut,
+        address=token_lottery.rollover_lottery@ErrorCode::IncorrectRolloverDestination
+    )]
+    pub next_lottery:Option<Box<Account<'info,TokenLottery>>>,
+
+    /// CHECK: the next lottery sol vault or the treasurer for lamport lotteries ,checked in the instruction
+    #[account(mut)]
+    pub destination:UncheckedAccount<'info>,
+
+    //the next lottery vault or a treasurer token account for token lotteries ,checked in the instruction 
+    #[account(
+        mut,
+        token::mint=payment_mint,
+        token::token_program=payment_token_program
+    )]
+    pub destination_token_account:Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+
+    //required when an unclaimed first tier carried a jackpot ,it is returned there 
+    #[account(
+        mut,
+        seeds=[b"jackpot".as_ref(),token_lottery.creator.as_ref(),token_lottery.payment_mint.as_ref()],
+        bump=jackpot.bump
+    )]
+    pub jackpot:Option<Box<Account<'info,Jackpot>>>,
+
+    #[account(
+        mut,
+        seeds=[b"jackpot_vault".as_ref(),token_lottery.creator.as_ref(),token_lottery.payment_mint.as_ref()],
+        bump,
+        token::token_program=payment_token_program
+    )]
+    pub jackpot_vault:Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+
+    #[account(address=token_lottery.payment_mint)]
+    pub payment_mint:Option<Box<InterfaceAccount<'info,Mint>>>,
+
+    #[account(
+        mut,
+        seeds=[b"vault".as_ref(),token_lottery.key().as_ref()],
+        bump,
+        token::token_program=payment_token_program
+    )]
+    pub vault:Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds=[b"sol_vault".as_ref(),token_lottery.key().as_ref()],
+        bump=token_lottery.sol_vault_bump
+    )]
+    pub sol_vault:SystemAccount<'info>,
+
+    pub payment_token_program:Option<Interface<'info,TokenInterface>>,
+
+    pub system_program:Program<'info,System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeSetRoles<'info>{
+    pub payer:Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"token_lottery".as_ref(),token_lottery.creator.as_ref(),token_lottery.lottery_id.to_le_bytes().as_ref()],
+        bump=token_lottery.bump
+    )]
+    pub token_lottery:Account<'info,TokenLottery>,
+}
+
+#[derive(Accounts)]
+pub struct InitializePause<'info>{
+    pub payer:Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"token_lottery".as_ref(),token_lottery.creator.as_ref(),token_lottery.lottery_id.to_le_bytes().as_ref()],
+        bump=token_lottery.bump
+    )]
+    pub token_lottery:Account<'info,TokenLottery>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeUnpause<'info>{
+    pub payer:Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"token_lottery".as_ref(),token_lottery.creator.as_ref(),token_lottery.lottery_id.to_le_bytes().as_ref()],
+        bump=token_lottery.bump
+    )]
+    pub token_lottery:Account<'info,TokenLottery>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeUpdateConfig<'info>{
+    pub payer:Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"token_lottery".as_ref(),token_lottery.creator.as_ref(),token_lottery.lottery_id.to_le_bytes().as_ref()],
+        bump=token_lottery.bump
+    )]
+    pub token_lottery:Account<'info,TokenLottery>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeBuyTickets<'info>{
+    #[account(mut)]
+    pub payer:Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"token_lottery".as_ref(),token_lottery.creator.as_ref(),token_lottery.lottery_id.to_le_bytes().as_ref()],
+        bump=token_lottery.bump
+    )]
+    pub token_lottery:Box<Account<'info,TokenLottery>>,
+
+    #[account(
+        init_if_needed,
+        payer=payer,
+        space=8+PurchaseRecord::INIT_SPACE,
+        seeds=[b"purchase_record".as_ref(),token_lottery.key().as_ref(),payer.key().as_ref()],
+        bump
+    )]
+    pub purchase_record:Box<Account<'info,PurchaseRecord>>,
+
+    //optional ,credits the referrer with referral_bps of this purchase 
+    #[account(
+        mut,
+        constraint=referral.lottery==token_lottery.key()@ErrorCode::IncorrectReferral,
+        constraint=referral.referrer!=payer.key()@ErrorCode::SelfReferral
+    )]
+    pub referral:Option<Box<Account<'info,Referral>>>,
+
+    //keyed by the first ticket number of the range so every bundle is unique 
+    #[account(
+        init,
+        payer=payer,
+        space=8+TicketBundle::INIT_SPACE,
+        seeds=[b"ticket_bundle".as_ref(),token_lottery.key().as_ref(),token_lottery.ticket_number.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub ticket_bundle:Box<Account<'info,TicketBundle>>,
+
+    #[account(address=token_lottery.payment_mint)]
+    pub payment_mint:Option<Box<InterfaceAccount<'info,Mint>>>,
+
+    #[account(
+        mut,
+        token::mint=payment_mint,
+        token::authority=payer,
+        token::token_program=payment_token_program
+    )]
+    pub payer_token_account:Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds=[b"vault".as_ref(),token_lottery.key().as_ref()],
+        bump,
+        token::token_program=payment_token_program
+    )]
+    pub vault:Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds=[b"sol_vault".as_ref(),token_lottery.key().as_ref()],
+        bump=token_lottery.sol_vault_bump
+    )]
+    pub sol_vault:SystemAccount<'info>,
+
+    pub payment_token_program:Option<Interface<'info,TokenInterface>>,
+
+    pub system_program:Program<'info,System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeClaimBundlePrize<'info>{
+    #[account(mut)]
+    pub payer:Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"token_lottery".as_ref(),token_lottery.creator.as_ref(),token_lottery.lottery_id.to_le_bytes().as_ref()],
+        bump=token_lottery.bump
+    )]
+    pub token_lottery:Box<Account<'info,TokenLottery>>,
+
+    #[account(
+        constraint=ticket_bundle.owner==payer.key()@ErrorCode::IncorrectTicket,
+        constraint=ticket_bundle.lottery==token_lottery.key()@ErrorCode::IncorrectTicket
+    )]
+    pub ticket_bundle:Box<Account<'info,TicketBundle>>,
+
+    #[account(address=token_lottery.payment_mint)]
+    pub payment_mint:Option<Box<InterfaceAccount<'info,Mint>>>,
+
+    #[account(
+        mut,
+        token::mint=payment_mint,
+        token::authority=payer,
+        token::token_program=payment_token_program
+    )]
+    pub payer_token_account:Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds=[b"vault".as_ref(),token_lottery.key().as_ref()],
+        bump,
+        token::token_program=payment_token_program
+    )]
+    pub vault:Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds=[b"sol_vault".as_ref(),token_lottery.key().as_ref()],
+        bump=token_lottery.sol_vault_bump
+    )]
+    pub sol_vault:SystemAccount<'info>,
+
+    pub payment_token_program:Option<Interface<'info,TokenInterface>>,
+
+    pub system_program:Program<'info,System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeRefundBundle<'info>{
+    #[account(mut)]
+    pub payer:Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"token_lottery".as_ref(),token_lottery.creator.as_ref(),token_lottery.lottery_id.to_le_bytes().as_ref()],
+        bump=token_lottery.bump
+    )]
+    pub token_lottery:Box<Account<'info,TokenLottery>>,
+
+    #[account(
+        mut,
+        constraint=ticket_bundle.owner==payer.key()@ErrorCode::IncorrectTicket,
+        constraint=ticket_bundle.lottery==token_lottery.key()@ErrorCode::IncorrectTicket
+    )]
+    pub ticket_bundle:Box<Account<'info,TicketBundle>>,
+
+    #[account(address=token_lottery.payment_mint)]
+    pub payment_mint:Option<Box<InterfaceAccount<'info,Mint>>>,
+
+    #[account(
+        mut,
+        token::mint=payment_mint,
+        token::authority=payer,
+        token::token_program=payment_token_program
+    )]
+    pub payer_token_account:Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds=[b"vault".as_ref(),token_lottery.key().as_ref()],
+        bump,
+        token::token_program=payment_token_program
+    )]
+    pub vault:Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds=[b"sol_vault".as_ref(),token_lottery.key().as_ref()],
+        bump=token_lottery.sol_vault_bump
+    )]
+    pub sol_vault:SystemAccount<'info>,
+
+    pub payment_token_program:Option<Interface<'info,TokenInterface>>,
+
+    pub system_program:Program<'info,System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeWithdrawFees<'info>{
+    #[account(mut)]
+    pub payer:Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"token_lottery".as_ref(),token_lottery.creator.as_ref(),token_lottery.lottery_id.to_le_bytes().as_ref()],
+        bump=token_lottery.bump
+    )]
+    pub token_lottery:Box<Account<'info,TokenLottery>>,
+
+    #[account(address=token_lottery.payment_mint)]
+    pub payment_mint:Option<Box<InterfaceAccount<'info,Mint>>>,
+
+    #[account(
+        mut,
+        token::mint=payment_mint,
+        token::authority=payer,
+        token::token_program=payment_token_program
+    )]
+    pub payer_token_account:Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds=[b"vault".as_ref(),token_lottery.key().as_ref()],
+        bump,
+        token::token_program=payment_token_program
+    )]
+    pub vault:Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds=[b"sol_vault".as_ref(),token_lottery.key().as_ref()],
+        bump=token_lottery.sol_vault_bump
+    )]
+    pub sol_vault:SystemAccount<'info>,
+
+    pub payment_token_program:Option<Interface<'info,TokenInterface>>,
+
+    pub system_program:Program<'info,System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeCreateJackpot<'info>{
+    #[account(mut)]
+    pub payer:Signer<'info>,
+
+    //any lottery of the creator ,only used for its creator and payment mint 
+    #[account(
+        seeds=[b"token_lottery".as_ref(),token_lottery.creator.as_ref(),token_lottery.lottery_id.to_le_bytes().as_ref()],
+        bump=token_lottery.bump
+    )]
+    pub token_lottery:Box<Account<'info,TokenLottery>>,
+
+    #[account(
+        init,
+        payer=payer,
+        space=8+Jackpot::INIT_SPACE,
+        seeds=[b"jackpot".as_ref(),token_lottery.creator.as_ref(),token_lottery.payment_mint.as_ref()],
+        bump
+    )]
+    pub jackpot:Box<Account<'info,Jackpot>>,
+
+    #[account(address=token_lottery.payment_mint)]
+    pub payment_mint:Option<Box<InterfaceAccount<'info,Mint>>>,
+
+    #[account(
+        init,
+        payer=payer,
+        seeds=[b"jackpot_vault".as_ref(),token_lottery.creator.as_ref(),token_lottery.payment_mint.as_ref()],
+        bump,
+        token::mint=payment_mint,
+        token::authority=jackpot,
+        token::token_program=payment_token_program
+    )]
+    pub jackpot_vault:Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+
+    pub payment_token_program:Option<Interface<'info,TokenInterface>>,
+
+    pub system_program:Program<'info,System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeRegisterReferrer<'info>{
+    #[account(mut)]
+    pub payer:Signer<'info>,
+
+    #[account(
+        seeds=[b"token_lottery".as_ref(),token_lottery.creator.as_ref(),token_lottery.lottery_id.to_le_bytes().as_ref()],
+        bump=token_lottery.bump
+    )]
+    pub token_lottery:Box<Account<'info,TokenLottery>>,
+
+    #[account(
+        init,
+        payer=payer,
+        space=8+Referral::INIT_SPACE,
+        seeds=[b"referral".as_ref(),token_lottery.key().as_ref(),payer.key().as_ref()],
+        bump
+    )]
+    pub referral:Box<Account<'info,Referral>>,
+
+    pub system_program:Program<'info,System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeClaimReferralRewards<'info>{
+    #[account(mut)]
+    pub payer:Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"token_lottery".as_ref(),token_lottery.creator.as_ref(),token_lottery.lottery_id.to_le_bytes().as_ref()],
+        bump=token_lottery.bump
+    )]
+    pub token_lottery:Box<Account<'info,TokenLottery>>,
+
+    #[account(
+        mut,
+        seeds=[b"referral".as_ref(),token_lottery.key().as_ref(),payer.key().as_ref()],
+        bump=referral.bump
+    )]
+    pub referral:Box<Account<'info,Referral>>,
+
+    #[account(address=token_lottery.payment_mint)]
+    pub payment_mint:Option<Box<InterfaceAccount<'info,Mint>>>,
+
+    #[account(
+        mut,
+        token::mint=payment_mint,
+        token::authority=payer,
+        token::token_program=payment_token_program
+    )]
+    pub payer_token_account:Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds=[b"vault".as_ref(),token_lottery.key().as_ref()],
+        bump,
+        token::token_program=payment_token_program
+    )]
+    pub vault:Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds=[b"sol_vault".as_ref(),token_lottery.key().as_ref()],
+        bump=token_lottery.sol_vault_bump
+    )]
+    pub sol_vault:SystemAccount<'info>,
+
+    pub payment_token_program:Option<Interface<'info,TokenInterface>>,
+
+    pub system_program:Program<'info,System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeDistributeProceeds<'info>{
+    pub payer:Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"token_lottery".as_ref(),token_lottery.creator.as_ref(),token_lottery.lottery_id.to_le_bytes().as_ref()],
+        bump=token_lottery.bump
+    )]
+    pub token_lottery:Box<Account<'info,TokenLottery>>,
+
+    #[account(address=token_lottery.payment_mint)]
+    pub payment_mint:Option<Box<InterfaceAccount<'info,Mint>>>,
+
+    #[account(
+        mut,
+        seeds=[b"vault".as_ref(),token_lottery.key().as_ref()],
+        bump,
+        token::token_program=payment_token_program
+    )]
+    pub vault:Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds=[b"sol_vault".as_ref(),token_lottery.key().as_ref()],
+        bump=token_lottery.sol_vault_bump
+    )]
+    pub sol_vault:SystemAccount<'info>,
+
+    pub payment_token_program:Option<Interface<'info,TokenInterface>>,
+
+    pub system_program:Program<'info,System>,
+}
+
+
+
+#[account]    //this will stata that it is account which stored on chain  
+#[derive(InitSpace)]  //calulat derive space for the account  enusre we allocate enough bytes 
+pub struct TokenLottery{
+    pub bump:u8 ,
+    pub sol_vault_bump:u8,
+    pub lottery_id:u64,
+    pub creator:Pubkey,    //never changes ,used with lottery_id to derive the pda 
+    #[max_len(MAX_PRIZE_TIERS)]
+    pub winners:Vec<u64>,      //winning ticket number for each drawn tier 
+    #[max_len(MAX_PRIZE_TIERS)]
+    pub prize_tiers:Vec<u16>,    //weight of each tier ,a tier gets its weight over the sum of the drawn weights 
+    #[max_len(MAX_PRIZE_TIERS)]
+    pub tier_claimed:Vec<bool>,
+    pub prize_pool:u64,     //pot at the moment of the draw ,tiers are paid from this 
+    pub claim_window_slots:u64,
+    pub claim_deadline:u64,    //last slot a prize can be claimed ,set by the draw ,a pending redraw must draw by then 
+    pub rollover_lottery:Pubkey,    //receives the unclaimed pot ,Pubkey::default() sends it to the treasurer 
+    pub round_template:Pubkey,    //template this round was started from ,Pubkey::default() for configinitialize lotteries 
+    pub max_redraws:u8,    //0 rolls unclaimed prizes over ,otherwise unclaimed tiers are drawn again 
+    pub redraws:u8,
+    #[max_len(MAX_DRAWN_TICKETS)]
+    pub drawn_tickets:Vec<u64>,    //every ticket drawn so far ,never drawn again 
+    pub lottery_start:u64,
+    pub lottery_end:u64,
+    pub ticket_number:u64,
+    pub token_lottery_pot:u64,
+    pub seed_jackpot:u64,    //part of the pot rolled in from an earlier round ,never refunded to ticket buyers 
+    pub fee_bps:u16,    //cut of every ticket kept for the house ,never part of the pot 
+    pub fees_accrued:u64,    //fees not yet withdrawn by the treasurer 
+    #[max_len(MAX_BENEFICIARIES)]
+    pub beneficiaries:Vec<Beneficiary>,    //eg a charity taking 5000 bps in a 50/50 raffle 
+    pub beneficiary_proceeds:u64,    //collected for the beneficiaries ,paid out by distribute_proceeds 
+    pub referral_bps:u16,    //share of a referred ticket credited to the referrer 
+    pub referral_rewards:u64,    //credited to referrers and not claimed yet 
+    pub jackpot_bps:u16,    //share of every ticket set aside for the progressive jackpot 
+    pub jackpot_odds:u64,    //the jackpot draw hits with a chance of 1 in jackpot_odds 
+    pub jackpot_contribution:u64,    //set aside this round ,moved into the jackpot at the draw 
+    pub jackpot_prize:u64,    //jackpot won by this round ,paid with the first tier 
+    pub authority:Pubkey,    //admin ,changes config and cancels 
+    pub pending_authority:Pubkey,    //set by propose_authority until accepted 
+    pub draw_operator:Pubkey,    //commits randomness and draws 
+    pub permissionless_draw:bool,    //anyone may commit and draw ,not only the draw_operator 
+    pub crank_reward_bps:u16,    //share of the pot paid to whoever executes the draw 
+    pub treasurer:Pubkey,    //the only key allowed to move house funds 
+    pub guardian:Pubkey,    //may pause and unpause next to the authority 
+    pub paused:bool,
+    pub paused_at:u64,    //slot of the last pause ,the claim deadline is pushed back by the paused time 
+    pub price:u64,
+    pub min_tickets:u64,
+    pub max_tickets:u64,    //0 means no cap 
+    pub max_tickets_per_wallet:u64,   //0 means no cap 
+    pub randomness_account:Pubkey,
+    pub randomness_queue:Pubkey,    //switchboard queue every committed randomness account must belong to 
+    pub randomness_committed_slot:u64,
+    pub randomness_seed_slot:u64,    //seed slot of the committed randomness ,a later commit of the same account is rejected 
+    pub reveal_timeout_slots:u64,    //after this many slots without a reveal the randomness can be replaced 
+    #[max_len(MAX_RANDOMNESS_RECOMMITS)]
+    pub abandoned_randomness:Vec<Pubkey>,    //every randomness account replaced by recommit_randomness 
+    pub randomness_committer:Pubkey,    //who committed the current randomness account 
+    #[max_len(MAX_RANDOMNESS_RECOMMITS)]
+    pub abandoned_committers:Vec<Pubkey>,    //committers of the abandoned accounts ,never commit or draw again 
+    pub payment_mint:Pubkey,    //Pubkey::default() means the lottery is paid in lamports 
+    pub phase:LotteryPhase,
+    pub branding:LotteryBranding,
+}
+
+#[derive(AnchorSerialize,AnchorDeserialize,Clone,InitSpace)]
+pub struct Beneficiary{
+    pub wallet:Pubkey,
+    pub share_bps:u16,    //share of every ticket sold 
+}
+
+#[derive(AnchorSerialize,AnchorDeserialize,Clone,InitSpace)]
+pub struct LotteryCreator{
+    pub address:Pubkey,
+    pub share:u8,
+}
+
+#[derive(AnchorSerialize,AnchorDeserialize,Clone,InitSpace)]
+pub struct LotteryBranding{
+    #[max_len(MAX_LOTTERY_NAME_LEN)]
+    pub name:String,
+    #[max_len(10)]
+    pub symbol:String,
+    #[max_len(200)]
+    pub collection_uri:String,
+    #[max_len(180)]
+    pub ticket_base_uri:String,    //ticket uri is this plus the ticket number ,empty means reuse the collection uri 
+    pub seller_fee_basis_points:u16,
+    #[max_len(MAX_CREATORS)]
+    pub creators:Vec<LotteryCreator>,    //shares must add up to 100 when set 
+}
+
+impl LotteryBranding {
+    pub fn with_defaults(mut self)->Self {
+        if self.name.is_empty() {
+            self.name=NAME.to_string();
+        }
+        if self.symbol.is_empty() {
+            self.symbol=SYMBOL.to_string();
+        }
+        if self.collection_uri.is_empty() {
+            self.collection_uri=URI.to_string();
+        }
+        self
+    }
+
+    pub fn validate(&self)->Result<()> {
+        require!(self.name.len() <= MAX_LOTTERY_NAME_LEN as usize,ErrorCode::InvalidBranding);
+        require!(self.symbol.len() <= 10,ErrorCode::InvalidBranding);
+        require!(self.collection_uri.len() <= 200 && self.ticket_base_uri.len() <= 180,ErrorCode::InvalidBranding);
+        require!(self.seller_fee_basis_points <= BPS_DENOMINATOR,ErrorCode::InvalidBranding);
+        require!(self.creators.len() <= MAX_CREATORS as usize,ErrorCode::InvalidBranding);
+        require!(self.creators.is_empty() || self.creators.iter().map(|creator| creator.share as u32).sum::<u32>()==100,ErrorCode::InvalidBranding);
+        Ok(())
+    }
+
+    pub fn ticket_name(&self,ticket_number:u64)->String {
+        self.name.clone() + ticket_number.to_string().as_str()
+    }
+
+    pub fn ticket_uri(&self,ticket_number:u64)->String {
+        if self.ticket_base_uri.is_empty() {
+            return self.collection_uri.clone();
+        }
+        self.ticket_base_uri.clone() + ticket_number.to_string().as_str()
+    }
+
+    fn creators(&self)->Vec<Creator> {
+        self.creators.iter().map(|creator| Creator{
+            address:creator.address,
+            verified:false,
+            share:creator.share,
+        }).collect()
+    }
+
+    //the collection mint stays a creator so sign_metadata can verify it ,with no share once partners are set 
+    pub fn collection_data(&self,collection_mint:Pubkey,collection_mint_verified:bool)->DataV2 {
+        let mut creators=vec![Creator{
+            address:collection_mint,
+            verified:collection_mint_verified,
+            share:if self.creators.is_empty() { 100 } else { 0 },
+        }];
+        creators.extend(self.creators());
+
+        DataV2 {
+            name:self.name.clone(),
+            symbol:self.symbol.clone(),
+            uri:self.collection_uri.clone(),
+            seller_fee_basis_points:self.seller_fee_basis_points,
+            creators:Some(creators),
+            collection:None,
+            uses:None
+        }
+    }
+
+    pub fn ticket_data(&self,ticket_number:u64)->DataV2 {
+        DataV2 {
+            name:self.ticket_name(ticket_number),
+            symbol:self.symbol.clone(),
+            uri:self.ticket_uri(ticket_number),
+            seller_fee_basis_points:self.seller_fee_basis_points,
+            creators:if self.creators.is_empty() { None } else { Some(self.creators()) },
+            collection:None,
+            uses:None,
+        }
+    }
+}
+
+#[derive(AnchorSerialize,AnchorDeserialize,Clone,InitSpace)]
+pub struct LotteryParams{
+    pub start:u64,
+    pub end:u64,
+    pub price:u64,
+    pub min_tickets:u64,    //below this when sales close the lottery falls into refunds 
+    pub max_tickets:u64,    //0 means no cap 
+    pub max_tickets_per_wallet:u64,   //0 means no cap 
+    #[max_len(MAX_PRIZE_TIERS)]
+    pub prize_tiers:Vec<u16>,    //relative weight for each winner ,eg [5000,3000,2000] in bps or [1,1,1] for N equal shares 
+    pub randomness_queue:Pubkey,
+    pub reveal_timeout_slots:u64,
+    pub permissionless_draw:bool,
+    pub crank_reward_bps:u16,
+    pub fee_bps:u16,
+    #[max_len(MAX_BENEFICIARIES)]
+    pub beneficiaries:Vec<Beneficiary>,
+    pub referral_bps:u16,
+    pub claim_window_slots:u64,
+    pub max_redraws:u8,
+    pub jackpot_bps:u16,
+    pub jackpot_odds:u64,
+    pub draw_operator:Pubkey,    //commits randomness and draws ,never the authority or the treasurer 
+    pub treasurer:Pubkey,
+    pub guardian:Pubkey,
+}
+
+impl LotteryParams {
+    pub fn check_roles(&self,authority:Pubkey)->Result<()> {
+        require!(roles_separated(authority,self.draw_operator,self.treasurer),ErrorCode::RolesNotSeparated);
+        Ok(())
+    }
+
+    pub fn validate(&self)->Result<()> {
+        require!(self.start <= self.end,ErrorCode::InvalidLotteryWindow);
+        require!(self.reveal_timeout_slots > 0,ErrorCode::InvalidRevealTimeout);
+        require!(self.claim_window_slots > 0,ErrorCode::InvalidClaimWindow);
+        require!(self.max_redraws <= MAX_REDRAWS,ErrorCode::InvalidRedraws);
+        require!(self.crank_reward_bps <= MAX_CRANK_REWARD_BPS,ErrorCode::InvalidCrankReward);
+        require!(self.beneficiaries.len() <= MAX_BENEFICIARIES as usize,ErrorCode::InvalidBeneficiaries);
+        require!(self.beneficiaries.iter().all(|beneficiary| beneficiary.share_bps > 0),ErrorCode::InvalidBeneficiaries);
+        require!(
+            self.beneficiaries.iter().enumerate().all(|(index,beneficiary)|
+                !self.beneficiaries[..index].iter().any(|other| other.wallet==beneficiary.wallet)),
+            ErrorCode::InvalidBeneficiaries
+        );
+        //fee ,beneficiaries and referral come off the top ,something has to be left for the prize pot 
+        let beneficiary_bps=self.beneficiaries.iter().map(|beneficiary| beneficiary.share_bps as u32).sum::<u32>();
+        require!(
+            self.fee_bps as u32 + beneficiary_bps + self.referral_bps as u32 + (self.jackpot_bps as u32) < BPS_DENOMINATOR as u32,
+            ErrorCode::InvalidFee
+        );
+        require!(self.jackpot_bps==0 || self.jackpot_odds > 0,ErrorCode::InvalidJackpotOdds);
+        require!(self.max_tickets==0 || self.min_tickets <= self.max_tickets,ErrorCode::InvalidTicketLimits);
+        require!(!self.prize_tiers.is_empty() && self.prize_tiers.len() <= MAX_PRIZE_TIERS as usize,ErrorCode::InvalidPrizeTiers);
+        require!(self.prize_tiers.iter().all(|share| *share > 0),ErrorCode::InvalidPrizeTiers);
+        Ok(())
+    }
+}
+
+//every instruction checks the phase it expects and moves the lottery forward 
+#[derive(AnchorSerialize,AnchorDeserialize,Clone,Copy,PartialEq,Eq,InitSpace,Debug)]
+pub enum LotteryPhase{
+    Created,    //configured ,collection not initialized yet 
+    Open,    //tickets on sale between lottery_start and lottery_end 
+    SalesClosed,
+    RandomnessCommitted,
+    Drawn,    //winners known ,prizes being claimed 
+    Settled,    //every drawn tier claimed 
+    Cancelled,    //holders refund their tickets 
+}
+
+impl LotteryPhase {
+    pub fn can_cancel(&self)->bool {
+        matches!(self,LotteryPhase::Created | LotteryPhase::Open | LotteryPhase::SalesClosed | LotteryPhase::RandomnessCommitted)
+    }
+}
+
+//fields left as None keep their current value 
+#[derive(AnchorSerialize,AnchorDeserialize,Clone)]
+pub struct ConfigUpdate{
+    pub price:Option<u64>,
+    pub start:Option<u64>,
+    pub end:Option<u64>,
+    pub min_tickets:Option<u64>,
+    pub max_tickets:Option<u64>,
+    pub max_tickets_per_wallet:Option<u64>,
+    pub randomness_queue:Option<Pubkey>,
+    pub reveal_timeout_slots:Option<u64>,
+    pub permissionless_draw:Option<bool>,
+    pub crank_reward_bps:Option<u16>,
+    pub fee_bps:Option<u16>,
+    pub referral_bps:Option<u16>,
+    pub claim_window_slots:Option<u64>,
+    pub max_redraws:Option<u8>,
+    pub jackpot_bps:Option<u16>,
+    pub jackpot_odds:Option<u64>,
+}
+
+#[derive(AnchorSerialize,AnchorDeserialize,Clone)]
+pub struct ConfigValues{
+    pub price:u64,
+    pub lottery_start:u64,
+    pub lottery_end:u64,
+    pub min_tickets:u64,
+    pub max_tickets:u64,
+    pub max_tickets_per_wallet:u64,
+    pub randomness_queue:Pubkey,
+    pub reveal_timeout_slots:u64,
+    pub permissionless_draw:bool,
+    pub crank_reward_bps:u16,
+    pub fee_bps:u16,
+    pub referral_bps:u16,
+    pub claim_window_slots:u64,
+    pub max_redraws:u8,
+    pub jackpot_bps:u16,
+    pub jackpot_odds:u64,
+}
+
+#[event]
+pub struct LotteryConfigured{
+    pub lottery:Pubkey,
+    pub creator:Pubkey,
+    pub lottery_id:u64,
+    pub payment_mint:Pubkey,
+    pub config:ConfigValues,
+    pub prize_tiers:Vec<u16>,
+}
+
+#[event]
+pub struct ConfigUpdated{
+    pub lottery:Pubkey,
+    pub previous:ConfigValues,
+    pub current:ConfigValues,
+}
+
+#[event]
+pub struct CollectionInitialized{
+    pub lottery:Pubkey,
+    pub collection_mint:Pubkey,
+    pub name:String,
+    pub symbol:String,
+    pub uri:String,
+}
+
+#[event]
+pub struct CollectionUpdated{
+    pub lottery:Pubkey,
+    pub collection_mint:Pubkey,
+    pub name:String,
+    pub symbol:String,
+    pub uri:String,
+}
+
+#[event]
+pub struct TicketPurchased{
+    pub lottery:Pubkey,
+    pub buyer:Pubkey,
+    pub ticket_number:u64,
+    pub ticket_mint:Pubkey,
+    pub price:u64,    //what the vault received ,below the ticket price for transfer fee mints 
+}
+
+#[event]
+pub struct TicketsPurchased{
+    pub lottery:Pubkey,
+    pub buyer:Pubkey,
+    pub ticket_bundle:Pubkey,
+    pub first_ticket:u64,
+    pub quantity:u64,
+    pub cost:u64,    //what the vault received ,below price times quantity for transfer fee mints 
+}
+
+#[event]
+pub struct RandomnessCommitted{
+    pub lottery:Pubkey,
+    pub randomness_account:Pubkey,
+    pub seed_slot:u64,
+}
+
+#[event]
+pub struct RandomnessRecommitted{
+    pub lottery:Pubkey,
+    pub abandoned_randomness_account:Pubkey,
+    pub randomness_account:Pubkey,
+    pub seed_slot:u64,
+}
+
+#[event]
+pub struct WinnersDrawn{
+    pub lottery:Pubkey,
+    pub randomness:[u8;32],    //raw revealed value so anyone can recompute the draw 
+    pub winners:Vec<u64>,
+    pub prize_pool:u64,
+    pub crank:Pubkey,
+    pub crank_reward:u64,
+    pub redraw:u8,    //0 for the first draw 
+    pub jackpot_prize:u64,    //added to the first tier when the jackpot draw hit 
+}
+
+#[event]
+pub struct ProceedsDistributed{
+    pub lottery:Pubkey,
+    pub beneficiaries:Vec<Pubkey>,
+    pub amounts:Vec<u64>,
+}
+
+#[event]
+pub struct ReferrerRegistered{
+    pub lottery:Pubkey,
+    pub referrer:Pubkey,
+    pub referral:Pubkey,
+}
+
+#[event]
+pub struct ReferralRewardsClaimed{
+    pub lottery:Pubkey,
+    pub referrer:Pubkey,
+    pub amount:u64,
+}
+
+#[event]
+pub struct JackpotWon{
+    pub jackpot:Pubkey,
+    pub lottery:Pubkey,
+    pub winning_ticket:u64,
+    pub amount:u64,
+}
+
+#[event]
+pub struct JackpotReturned{
+    pub jackpot:Pubkey,
+    pub lottery:Pubkey,
+    pub amount:u64,
+}
+
+#[event]
+pub struct JackpotCreated{
+    pub jackpot:Pubkey,
+    pub creator:Pubkey,
+    pub payment_mint:Pubkey,
+}
+
+#[event]
+pub struct RoundTemplateCreated{
+    pub round_template:Pubkey,
+    pub authority:Pubkey,
+    pub template_id:u64,
+    pub duration_slots:u64,
+    pub payment_mint:Pubkey,
+}
+
+#[event]
+pub struct RoundStarted{
+    pub round_template:Pubkey,
+    pub lottery:Pubkey,
+    pub round:u64,
+    pub seed_jackpot:u64,
+}
+
+#[event]
+pub struct RedrawRequested{
+    pub lottery:Pubkey,
+    pub redraw:u8,
+    pub unclaimed_tiers:u8,
+}
+
+#[event]
+pub struct RolloverLotterySet{
+    pub lottery:Pubkey,
+    pub rollover_lottery:Pubkey,
+}
+
+#[event]
+pub struct UnclaimedRolledOver{
+    pub lottery:Pubkey,
+    pub destination:Pubkey,    //next lottery or the treasurer 
+    pub amount:u64,
+}
+
+#[event]
+pub struct FeesWithdrawn{
+    pub lottery:Pubkey,
+    pub treasurer:Pubkey,
+    pub amount:u64,
+}
+
+#[event]
+pub struct PrizeClaimed{
+    pub lottery:Pubkey,
+    pub winner:Pubkey,
+    pub tier:u8,
+    pub ticket_number:u64,
+    pub amount:u64,
+}
+
+#[event]
+pub struct LotteryCancelled{
+    pub lottery:Pubkey,
+    pub authority:Pubkey,
+    pub pot:u64,    //everything left for refunds ,fees and shares folded back in 
+}
+
+#[event]
+pub struct TicketRefunded{
+    pub lottery:Pubkey,
+    pub owner:Pubkey,
+    pub ticket_number:u64,
+    pub amount:u64,
+}
+
+#[event]
+pub struct BundleRefunded{
+    pub lottery:Pubkey,
+    pub owner:Pubkey,
+    pub ticket_bundle:Pubkey,
+    pub first_ticket:u64,
+    pub quantity:u64,
+    pub amount:u64,
+}
+
+#[event]
+pub struct LotteryPaused{
+    pub lottery:Pubkey,
+    pub by:Pubkey,    //authority or guardian 
+    pub slot:u64,
+}
+
+#[event]
+pub struct LotteryUnpaused{
+    pub lottery:Pubkey,
+    pub by:Pubkey,
+    pub claim_deadline:u64,    //pushed back by the paused slots once drawn 
+}
+
+#[event]
+pub struct AuthorityProposed{
+    pub lottery:Pubkey,
+    pub authority:Pubkey,
+    pub pending_authority:Pubkey,
+}
+
+#[event]
+pub struct AuthorityAccepted{
+    pub lottery:Pubkey,
+    pub previous_authority:Pubkey,
+    pub authority:Pubkey,
+}
 
-    pub token_lottery:Account<'info,TokenLottery>,
-   
-   /// CHECK: This account will be initialized by the metaplex program
-    pub randomness_account_data:UncheckedAccount<'info>,
-  
-    pub system_program:Program<'info,System>
+#[event]
+pub struct RolesUpdated{
+    pub lottery:Pubkey,
+    pub draw_operator:Pubkey,
+    pub treasurer:Pubkey,
+    pub guardian:Pubkey,
 }
 
-#[derive(Accounts)]
-pub struct InitializeClaimPrize<'info>{
-   #[account(mut)]
-   pub payer:Signer<'info> ,
-    
+#[account]
+#[derive(InitSpace)]
+pub struct PurchaseRecord{
+    pub lottery:Pubkey,
+    pub buyer:Pubkey,
+    pub tickets_bought:u64,
+    pub bump:u8,
+}
 
-   #[account(
-    mut,
-    seeds=[b"token_lottery".as_ref()],
-    bump=token_lottery.bump
- )]
+impl PurchaseRecord {
+    pub fn record(&mut self,token_lottery:&TokenLottery,quantity:u64)->Result<()> {
+        let bought=self.tickets_bought.checked_add(quantity).ok_or(ErrorCode::MathOverflow)?;
+        if token_lottery.max_tickets_per_wallet > 0 {
+            require!(bought <= token_lottery.max_tickets_per_wallet,ErrorCode::WalletTicketLimitReached);
+        }
+        self.tickets_bought=bought;
+        Ok(())
+    }
+}
 
-pub token_lottery:Account<'info,TokenLottery>, 
+//settings every round of a recurring lottery starts from ,start and end of `params` are replaced per round 
+#[account]
+#[derive(InitSpace)]
+pub struct RoundTemplate{
+    pub authority:Pubkey,    //becomes the authority of every round 
+    pub template_id:u64,
+    pub duration_slots:u64,
+    pub params:LotteryParams,
+    pub branding:LotteryBranding,
+    pub payment_mint:Pubkey,
+    pub round:u64,    //last round started ,0 before the first one 
+    pub current_lottery:Pubkey,
+    pub bump:u8,
+}
 
-#[account(
-    mut,
-    seeds=[b"collection_mint".as_ref()],
-    bump
-)]
+//progressive jackpot shared by every lottery of a creator ,lamports sit on this account above its rent 
+//and tokens in the jackpot_vault ,balance is all that can ever leave 
+#[account]
+#[derive(InitSpace)]
+pub struct Jackpot{
+    pub creator:Pubkey,
+    pub payment_mint:Pubkey,
+    pub balance:u64,
+    pub total_paid:u64,
+    pub bump:u8,
+}
 
-pub collection_mint:InterfaceAccount<'info,Mint> ,
+//one per referrer and lottery ,rewards stay in the lottery vault until claimed 
+#[account]
+#[derive(InitSpace)]
+pub struct Referral{
+    pub lottery:Pubkey,
+    pub referrer:Pubkey,
+    pub claimable:u64,
+    pub total_earned:u64,
+    pub bump:u8,
+}
 
+impl Referral {
+    pub fn credit(&mut self,amount:u64)->Result<()> {
+        self.claimable=self.claimable.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+        self.total_earned=self.total_earned.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+}
 
-#[account(
-    seeds=[b"metadata",token_metadata_program.key().as_ref(),ticket_mint.key().as_ref()],
-    bump,
-    seeds::program=token_metadata_program.key(),
-)]
+//non nft entries bought through buy_tickets ,tickets first_ticket..first_ticket+quantity 
+#[account]
+#[derive(InitSpace)]
+pub struct TicketBundle{
+    pub lottery:Pubkey,
+    pub owner:Pubkey,
+    pub first_ticket:u64,
+    pub quantity:u64,
+    pub refunded:bool,
+    pub bump:u8,
+}
 
-pub metadata:Account<'info,MetadataAccount> ,
+impl TicketBundle {
+    pub fn contains(&self,ticket:u64)->bool {
+        ticket >= self.first_ticket && ticket - self.first_ticket < self.quantity
+    }
+}
 
-#[account(
-    seeds=[token_lottery.winner.to_le_bytes().as_ref()],
-    bump
-)]
-pub ticket_mint:InterfaceAccount<'info,Mint> ,
- 
- #[account(
-    associated_token::mint=ticket_mint,
-    associated_token::authority=payer,
-    associated_token::token_program=token_program,
- )]
+#[account]
+#[derive(InitSpace)]
+pub struct TicketRefund{
+    pub ticket_number:u64,
+    pub bump:u8,
+}
 
-pub destination:InterfaceAccount<'info,TokenAccount>,
+impl TokenLottery {
+    pub fn pays_in_tokens(&self)->bool {
+        self.payment_mint != Pubkey::default()
+    }
 
-#[account(
-    mut,
-    seeds=[b"metadata",token_metadata_program.key().as_ref(),collection_mint.key().as_ref()],
-    bump,
-    seeds::program=token_metadata_program.key()
-)]
+    //every field configinitialize and start_next_round share ,params are validated by the caller 
+    pub fn configure(&mut self,creator:Pubkey,lottery_id:u64,authority:Pubkey,params:LotteryParams) {
+        self.lottery_id=lottery_id;
+        self.creator=creator;
+        self.lottery_start=params.start;
+        self.lottery_end=params.end;
+        self.price=params.price;
+        self.min_tickets=params.min_tickets;
+        self.max_tickets=params.max_tickets;
+        self.max_tickets_per_wallet=params.max_tickets_per_wallet;
+        self.authority=authority;
+        self.pending_authority=Pubkey::default();
+        self.draw_operator=params.draw_operator;
+        self.treasurer=params.treasurer;
+        self.guardian=params.guardian;
+        self.paused=false;
+        self.paused_at=0;
+        self.ticket_number=0;     //how mant tickets have been sold
+        self.phase=LotteryPhase::Created;
+        self.prize_tiers=params.prize_tiers;
+        self.winners=Vec::new();
+        self.tier_claimed=Vec::new();
+        self.randomness_account=Pubkey::default();
+        self.randomness_queue=params.randomness_queue;
+        self.reveal_timeout_slots=params.reveal_timeout_slots;
+        self.permissionless_draw=params.permissionless_draw;
+        self.crank_reward_bps=params.crank_reward_bps;
+        self.fee_bps=params.fee_bps;
+        self.fees_accrued=0;
+        self.beneficiaries=params.beneficiaries;
+        self.beneficiary_proceeds=0;
+        self.referral_bps=params.referral_bps;
+        self.referral_rewards=0;
+        self.jackpot_bps=params.jackpot_bps;
+        self.jackpot_odds=params.jackpot_odds;
+        self.jackpot_contribution=0;
+        self.jackpot_prize=0;
+        self.claim_window_slots=params.claim_window_slots;
+        self.claim_deadline=0;
+        self.rollover_lottery=Pubkey::default();
+        self.max_redraws=params.max_redraws;
+        self.redraws=0;
+        self.drawn_tickets=Vec::new();
+    }
 
- 
-pub collection_metadata:Account<'info,MetadataAccount>,
+    pub fn config_values(&self)->ConfigValues {
+        ConfigValues{
+            price:self.price,
+            lottery_start:self.lottery_start,
+            lottery_end:self.lottery_end,
+            min_tickets:self.min_tickets,
+            max_tickets:self.max_tickets,
+            max_tickets_per_wallet:self.max_tickets_per_wallet,
+            randomness_queue:self.randomness_queue,
+            reveal_timeout_slots:self.reveal_timeout_slots,
+            permissionless_draw:self.permissionless_draw,
+            crank_reward_bps:self.crank_reward_bps,
+            fee_bps:self.fee_bps,
+            referral_bps:self.referral_bps,
+            claim_window_slots:self.claim_window_slots,
+            max_redraws:self.max_redraws,
+            jackpot_bps:self.jackpot_bps,
+            jackpot_odds:self.jackpot_odds,
+        }
+    }
 
-pub token_program:InterfaceAccount<'info,TokenAccount>,
-pub token_metadata_program:Program<'info,Metadata>,
-pub system_program:Program<'info,System> ,
-  
+    pub fn can_draw(&self,key:Pubkey)->bool {
+        self.permissionless_draw || key==self.draw_operator
+    }
+
+    //once a commitment was replaced anyone not barred may draw ,a barred draw operator cannot stall the lottery 
+    pub fn can_draw_committed(&self,key:Pubkey)->bool {
+        !self.abandoned_committers.contains(&key) &&
+            (self.can_draw(key) || !self.abandoned_committers.is_empty())
+    }
+
+    pub fn crank_reward(&self)->u64 {
+        (self.token_lottery_pot as u128 * self.crank_reward_bps as u128 / BPS_DENOMINATOR as u128) as u64
+    }
+
+    pub fn can_pause(&self,key:Pubkey)->bool {
+        key==self.authority || key==self.guardian
+    }
+
+    pub fn require_phase(&self,phase:LotteryPhase)->Result<()> {
+        require!(self.phase==phase,ErrorCode::InvalidLotteryPhase);
+        Ok(())
+    }
+
+    //nothing runs at lottery_end ,the first instruction after it moves Open to SalesClosed 
+    pub fn close_sales_if_ended(&mut self,slot:u64) {
+        if self.phase==LotteryPhase::Open && slot > self.lottery_end {
+            self.phase=LotteryPhase::SalesClosed;
+        }
+    }
+
+    pub fn check_sales_open(&self,slot:u64)->Result<()> {
+        require!(self.phase==LotteryPhase::Open,ErrorCode::LotteryNotOpen);
+        require!(slot >= self.lottery_start && slot <= self.lottery_end,ErrorCode::LotteryNotOpen);
+        Ok(())
+    }
+
+    //the oracle had reveal_timeout_slots after the commit to reveal ,only then may the commitment be given up 
+    pub fn check_reveal_timeout(&self,slot:u64)->Result<()> {
+        let timeout=self.randomness_committed_slot.checked_add(self.reveal_timeout_slots).ok_or(ErrorCode::MathOverflow)?;
+        require!(slot > timeout,ErrorCode::RevealTimeoutNotReached);
+        Ok(())
+    }
+
+    //switchboard lets whoever controls a randomness account commit it again ,only the commitment
+    //the lottery saw counts or the controller could keep re-rolling until a reveal suits them 
+    pub fn check_committed_seed(&self,randomness:&RandomnessAccountData)->Result<()> {
+        require!(randomness.seed_slot==self.randomness_seed_slot,ErrorCode::RandomnessSeedChanged);
+        Ok(())
+    }
+
+    //randomness must come from the configured queue and be seeded in the previous slot ,after sales closed 
+    pub fn check_fresh_commitment(&self,randomness:&RandomnessAccountData,slot:u64)->Result<()> {
+        require!(randomness.queue==self.randomness_queue,ErrorCode::IncorrectRandomnessQueue);
+        //seeded before sales closed means the outcome could have been known while tickets were still sold 
+        require!(randomness.seed_slot > self.lottery_end,ErrorCode::RandomnessCommittedTooEarly);
+        require!(randomness.seed_slot==slot.saturating_sub(1),ErrorCode::RandomnessNotRevealed);
+        Ok(())
+    }
+
+    pub fn check_ticket_supply(&self,quantity:u64)->Result<()> {
+        if self.max_tickets > 0 {
+            let sold=self.ticket_number.checked_add(quantity).ok_or(ErrorCode::MathOverflow)?;
+            require!(sold <= self.max_tickets,ErrorCode::TicketsSoldOut);
+        }
+        Ok(())
+    }
+
+    //marks the tier claimed and takes its prize out of the pot 
+    pub fn claim_tier(&mut self,tier:usize,slot:u64)->Result<u64> {
+        require!(self.phase==LotteryPhase::Drawn,ErrorCode::WinnerNotChosen);
+        require!(slot <= self.claim_deadline,ErrorCode::ClaimDeadlinePassed);
+        require!(tier < self.winners.len(),ErrorCode::InvalidPrizeTier);
+        require!(!self.tier_claimed[tier],ErrorCode::PrizeAlreadyClaimed);
+
+        let prize=self.tier_prize(tier)?;
+        self.token_lottery_pot=self.token_lottery_pot.checked_sub(prize).ok_or(ErrorCode::MathOverflow)?;
+        self.tier_claimed[tier]=true;
+
+        if self.tier_claimed.iter().all(|claimed| *claimed) {
+            self.phase=LotteryPhase::Settled;
+        }
+        Ok(prize)
+    }
+
+    //winners could not claim while paused ,give them back the paused slots that fell after the draw 
+    //the claim window opened at claim_deadline - claim_window_slots ,earlier extensions only move it later 
+    pub fn extend_claim_deadline(&mut self,slot:u64)->Result<()> {
+        if self.claim_deadline==0 {
+            return Ok(());
+        }
+        let window_open=self.claim_deadline.saturating_sub(self.claim_window_slots);
+        let paused_slots=slot.saturating_sub(self.paused_at.max(window_open));
+        self.claim_deadline=self.claim_deadline.checked_add(paused_slots).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn unclaimed_tiers(&self)->usize {
+        self.tier_claimed.iter().filter(|claimed| !**claimed).count()
+    }
+
+    //redraws left and enough never drawn tickets to replace every unclaimed tier 
+    pub fn can_redraw(&self)->bool {
+        self.redraws < self.max_redraws &&
+            self.ticket_number.saturating_sub(self.drawn_tickets.len() as u64) >= self.unclaimed_tiers() as u64
+    }
+
+    pub fn beneficiary_bps(&self)->u32 {
+        self.beneficiaries.iter().map(|beneficiary| beneficiary.share_bps as u32).sum()
+    }
+
+    //fee ,beneficiaries and referral come off the top ,something has to be left for the prize pot 
+    pub fn check_waterfall(&self)->Result<()> {
+        require!(
+            self.fee_bps as u32 + self.beneficiary_bps() + self.referral_bps as u32 + (self.jackpot_bps as u32) < BPS_DENOMINATOR as u32,
+            ErrorCode::InvalidFee
+        );
+        Ok(())
+    }
+
+    //splits what a buyer paid between the house fee ,the beneficiaries ,the referrer ,the jackpot and the prize pot 
+    //returns the referral reward ,without a referrer that share stays in the pot 
+    pub fn deposit(&mut self,received:u64,referred:bool)->Result<u64> {
+        let fee=(received as u128 * self.fee_bps as u128 / BPS_DENOMINATOR as u128) as u64;
+        let proceeds=(received as u128 * self.beneficiary_bps() as u128 / BPS_DENOMINATOR as u128) as u64;
+        let referral_reward=if referred {
+            (received as u128 * self.referral_bps as u128 / BPS_DENOMINATOR as u128) as u64
+        } else {
+            0
+        };
+        let jackpot=(received as u128 * self.jackpot_bps as u128 / BPS_DENOMINATOR as u128) as u64;
+        self.fees_accrued=self.fees_accrued.checked_add(fee).ok_or(ErrorCode::MathOverflow)?;
+        self.beneficiary_proceeds=self.beneficiary_proceeds.checked_add(proceeds).ok_or(ErrorCode::MathOverflow)?;
+        self.referral_rewards=self.referral_rewards.checked_add(referral_reward).ok_or(ErrorCode::MathOverflow)?;
+        self.jackpot_contribution=self.jackpot_contribution.checked_add(jackpot).ok_or(ErrorCode::MathOverflow)?;
+        self.token_lottery_pot=self.token_lottery_pot.checked_add(received-fee-proceeds-referral_reward-jackpot).ok_or(ErrorCode::MathOverflow)?;
+        Ok(referral_reward)
+    }
+
+    //fees ,beneficiary proceeds ,referral rewards and the jackpot share are only earned by a lottery that draws 
+    //on cancel they go back into the pot for refunds 
+    pub fn cancel(&mut self) {
+        self.phase=LotteryPhase::Cancelled;
+        self.token_lottery_pot+=self.fees_accrued+self.beneficiary_proceeds+self.referral_rewards+self.jackpot_contribution;
+        self.fees_accrued=0;
+        self.beneficiary_proceeds=0;
+        self.referral_rewards=0;
+        self.jackpot_contribution=0;
+    }
+
+    //beneficiary_proceeds split by share ,the last beneficiary takes the rounding dust 
+    pub fn beneficiary_amounts(&self)->Vec<u64> {
+        let total_bps=self.beneficiary_bps() as u128;
+        let mut remaining=self.beneficiary_proceeds;
+        self.beneficiaries.iter().enumerate().map(|(index,beneficiary)| {
+            let amount=if index+1==self.beneficiaries.len() {
+                remaining
+            } else {
+                (self.beneficiary_proceeds as u128 * beneficiary.share_bps as u128 / total_bps) as u64
+            };
+            remaining-=amount;
+            amount
+        }).collect()
+    }
+
+    //a lottery that closed below min_tickets switches into refunds on the first refund 
+    //with transfer fee mints the pot can hold a bit less than price per ticket ,so the refund is capped by it 
+    //the seed jackpot was never paid by the buyers and stays for the next round 
+    pub fn take_refund(&mut self,tickets:u64,slot:u64)->Result<u64> {
+        self.close_sales_if_ended(slot);
+        if self.is_undersubscribed(slot) {
+            self.cancel();
+        }
+        require!(self.phase==LotteryPhase::Cancelled,ErrorCode::LotteryNotCancelled);
+
+        let refundable=self.token_lottery_pot.saturating_sub(self.seed_jackpot);
+        let refund=self.price.checked_mul(tickets).ok_or(ErrorCode::MathOverflow)?.min(refundable);
+        self.token_lottery_pot -=refund;
+        Ok(refund)
+    }
+
+    //sales are over and not enough tickets were sold for a draw ,with min_tickets 0 a round without tickets counts too 
+    pub fn is_undersubscribed(&self,slot:u64)->bool {
+        matches!(self.phase,LotteryPhase::Created | LotteryPhase::Open | LotteryPhase::SalesClosed) &&
+            slot > self.lottery_end && (self.ticket_number < self.min_tickets || self.ticket_number==0)
+    }
 
+    //u64::MAX for a tier that was not drawn so the ticket mint seeds never resolve 
+    pub fn winning_ticket(&self,tier:u8)->u64 {
+        self.winners.get(tier as usize).copied().unwrap_or(u64::MAX)
+    }
+
+    //when fewer tickets sold than tiers only the drawn tiers share the pool 
+    pub fn tier_prize(&self,tier:usize)->Result<u64> {
+        let drawn_shares:u64=self.prize_tiers[..self.winners.len()].iter().map(|share| *share as u64).sum();
+        let prize=(self.prize_pool as u128)
+            .checked_mul(self.prize_tiers[tier] as u128)
+            .and_then(|value| value.checked_div(drawn_shares as u128))
+            .ok_or(ErrorCode::MathOverflow)?;
+        //a won jackpot goes to the first tier on top of its share 
+        if tier==0 {
+            return Ok((prize as u64).checked_add(self.jackpot_prize).ok_or(ErrorCode::MathOverflow)?);
+        }
+        Ok(prize as u64)
+    }
 }
 
+//same rule for roles passed at creation and roles changed later by set_roles 
+fn roles_separated(authority:Pubkey,draw_operator:Pubkey,treasurer:Pubkey)->bool {
+    draw_operator != authority && draw_operator != treasurer
+}
 
+//the local validator clones the mainnet program ,devnet runs its own deployment 
+fn is_switchboard_account(owner:&Pubkey)->bool {
+    *owner==ON_DEMAND_MAINNET_PID || *owner==ON_DEMAND_DEVNET_PID
+}
 
-#[account]    //this will stata that it is account which stored on chain  
-#[derive(InitSpace)]  //calulat derive space for the account  enusre we allocate enough bytes 
-pub struct TokenLottery{
-    pub bump:u8 ,
-    pub winner:u64,
-    pub lottery_start:u64,
-    pub lottery_end:u64,
-    pub winner_chosen:bool,
-    pub ticket_number:u64,
-    pub token_lottery_pot:u64,
-    pub authority:Pubkey,
-    pub price:u64,
-    pub randomness_account:Pubkey
+//uniform ticket in 0..ticket_count from a 32 byte value 
+//every 8 byte word is read as a u64 and words below 2^64 mod ticket_count are rejected ,the rest is an exact
+//multiple of ticket_count so the modulo has no bias ,if all four words are rejected the value is rehashed 
+fn ticket_from_randomness(randomness:&[u8;32],ticket_count:u64)->u64 {
+    let threshold=ticket_count.wrapping_neg() % ticket_count;
+    let mut words=*randomness;
+    loop {
+        for chunk in words.chunks_exact(8) {
+            let value=u64::from_le_bytes(chunk.try_into().unwrap());
+            if value >= threshold {
+                return value % ticket_count;
+            }
+        }
+        words=hashv(&[words.as_ref()]).to_bytes();
+    }
+}
+
+//picks `count` distinct ticket numbers ,every attempt rehashes the revealed value with a counter 
+//and numbers already drawn are skipped so a ticket can only win one tier 
+//`excluded` holds tickets drawn in earlier rounds ,callers make sure enough tickets are left 
+fn draw_winners(randomness:&[u8;32],ticket_count:u64,count:usize,excluded:&[u64])->Vec<u64> {
+    let mut winners:Vec<u64>=Vec::with_capacity(count);
+    let mut nonce:u64=0;
+    while winners.len() < count {
+        let derived=hashv(&[randomness.as_ref(),nonce.to_le_bytes().as_ref()]).to_bytes();
+        let ticket=ticket_from_randomness(&derived,ticket_count);
+        if !winners.contains(&ticket) && !excluded.contains(&ticket) {
+            winners.push(ticket);
+        }
+        nonce +=1;
+    }
+    winners
+}
+
+//moves `amount` from the buyer into the lottery and returns what actually arrived 
+//for token 2022 mints with a transfer fee this is less than `amount` 
+#[allow(clippy::too_many_arguments)]
+fn collect_payment<'info>(
+    token_lottery:&Account<'info,TokenLottery>,
+    payer:&Signer<'info>,
+    system_program:&Program<'info,System>,
+    sol_vault:&SystemAccount<'info>,
+    payment_mint:&Option<Box<InterfaceAccount<'info,Mint>>>,
+    payer_token_account:&Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+    vault:&mut Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+    payment_token_program:&Option<Interface<'info,TokenInterface>>,
+    amount:u64,
+)->Result<u64> {
+    if !token_lottery.pays_in_tokens() {
+        system_program::transfer(CpiContext::new(
+            system_program.to_account_info(),
+            system_program::Transfer{
+                from:payer.to_account_info(),
+                to:sol_vault.to_account_info()
+            }
+        ),amount)?;
+        return Ok(amount);
+    }
+
+    let (Some(payment_mint),Some(payer_token_account),Some(vault),Some(payment_token_program))=
+        (payment_mint,payer_token_account,vault.as_mut(),payment_token_program) else {
+        return Err(ErrorCode::MissingPaymentAccounts.into());
+    };
+
+    let before=vault.amount;
+    transfer_checked(CpiContext::new(
+        payment_token_program.to_account_info(),
+        TransferChecked{
+            from:payer_token_account.to_account_info(),
+            mint:payment_mint.to_account_info(),
+            to:vault.to_account_info(),
+            authority:payer.to_account_info()
+        }
+    ),amount,payment_mint.decimals)?;
+
+    vault.reload()?;
+    Ok(vault.amount.checked_sub(before).ok_or(ErrorCode::MathOverflow)?)
+}
+
+//moves this round's contribution into the jackpot ,then a second draw independent of the winners decides
+//whether the whole jackpot joins the first tier prize ,returns the jackpot won 
+fn settle_jackpot<'info>(accounts:&mut InitializeChooseWinner<'info>,randomness:&[u8;32])->Result<u64> {
+    let jackpot=accounts.jackpot.as_mut().ok_or(ErrorCode::MissingJackpotAccount)?;
+
+    let contribution=accounts.token_lottery.jackpot_contribution;
+    accounts.token_lottery.jackpot_contribution=0;
+    if contribution > 0 {
+        pay_out(
+            &accounts.token_lottery,
+            &jackpot.to_account_info(),
+            &accounts.sol_vault,
+            &accounts.system_program,
+            &accounts.payment_mint,
+            &accounts.jackpot_vault,
+            &accounts.vault,
+            &accounts.payment_token_program,
+            contribution,
+        )?;
+        jackpot.balance=jackpot.balance.checked_add(contribution).ok_or(ErrorCode::MathOverflow)?;
+    }
+
+    let jackpot_draw=hashv(&[randomness.as_ref(),b"jackpot".as_ref()]).to_bytes();
+    if jackpot.balance==0 || ticket_from_randomness(&jackpot_draw,accounts.token_lottery.jackpot_odds)!=0 {
+        return Ok(0);
+    }
+
+    let prize=jackpot.balance;
+    jackpot.balance=0;
+    jackpot.total_paid=jackpot.total_paid.checked_add(prize).ok_or(ErrorCode::MathOverflow)?;
+
+    if accounts.token_lottery.pays_in_tokens() {
+        let (Some(payment_mint),Some(jackpot_vault),Some(vault),Some(payment_token_program))=
+            (&accounts.payment_mint,&accounts.jackpot_vault,&accounts.vault,&accounts.payment_token_program) else {
+            return Err(ErrorCode::MissingPaymentAccounts.into());
+        };
+        let signer_seeds:&[&[&[u8]]]=&[&[
+            b"jackpot".as_ref(),
+            jackpot.creator.as_ref(),
+            jackpot.payment_mint.as_ref(),
+            &[jackpot.bump],
+        ]];
+        transfer_checked(CpiContext::new_with_signer(
+            payment_token_program.to_account_info(),
+            TransferChecked{
+                from:jackpot_vault.to_account_info(),
+                mint:payment_mint.to_account_info(),
+                to:vault.to_account_info(),
+                authority:jackpot.to_account_info()
+            },
+            signer_seeds,
+        ),prize,payment_mint.decimals)?;
+    } else {
+        //the program owns the jackpot account so it can debit it directly ,never below balance 
+        **jackpot.to_account_info().try_borrow_mut_lamports()? -=prize;
+        **accounts.sol_vault.to_account_info().try_borrow_mut_lamports()? +=prize;
+    }
+
+    let token_lottery=&mut accounts.token_lottery;
+    token_lottery.jackpot_prize=prize;
+    token_lottery.token_lottery_pot=token_lottery.token_lottery_pot.checked_add(prize).ok_or(ErrorCode::MathOverflow)?;
+
+    emit!(JackpotWon{
+        jackpot:jackpot.key(),
+        lottery:token_lottery.key(),
+        winning_ticket:token_lottery.winning_ticket(0),
+        amount:prize,
+    });
+
+    Ok(prize)
+}
+
+//the lamport vault holds its own rent reserve so payouts never dip into it 
+fn fund_sol_vault<'info>(
+    payer:&Signer<'info>,
+    sol_vault:&SystemAccount<'info>,
+    system_program:&Program<'info,System>,
+)->Result<()> {
+    let reserve=Rent::get()?.minimum_balance(0).saturating_sub(sol_vault.lamports());
+    if reserve > 0 {
+        system_program::transfer(CpiContext::new(
+            system_program.to_account_info(),
+            system_program::Transfer{
+                from:payer.to_account_info(),
+                to:sol_vault.to_account_info()
+            }
+        ),reserve)?;
+    }
+    Ok(())
+}
+
+//pays `amount` out of the lottery ,lamports go to `recipient` and tokens to `recipient_token_account` 
+//everything leaves through a vault ,the lottery account itself is never debited so its rent reserve stays put 
+#[allow(clippy::too_many_arguments)]
+fn pay_out<'info>(
+    token_lottery:&Account<'info,TokenLottery>,
+    recipient:&AccountInfo<'info>,
+    sol_vault:&SystemAccount<'info>,
+    system_program:&Program<'info,System>,
+    payment_mint:&Option<Box<InterfaceAccount<'info,Mint>>>,
+    recipient_token_account:&Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+    vault:&Option<Box<InterfaceAccount<'info,TokenAccount>>>,
+    payment_token_program:&Option<Interface<'info,TokenInterface>>,
+    amount:u64,
+)->Result<()> {
+    if !token_lottery.pays_in_tokens() {
+        //the vault keeps its own rent reserve ,only lamports above it can ever be paid 
+        let reserve=Rent::get()?.minimum_balance(0);
+        let remaining=sol_vault.lamports().checked_sub(amount).ok_or(ErrorCode::InsufficientVaultBalance)?;
+        require!(remaining >= reserve,ErrorCode::InsufficientVaultBalance);
+
+        let lottery=token_lottery.key();
+        let signer_seeds:&[&[&[u8]]]=&[&[
+            b"sol_vault".as_ref(),
+            lottery.as_ref(),
+            &[token_lottery.sol_vault_bump],
+        ]];
+        return system_program::transfer(CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            system_program::Transfer{
+                from:sol_vault.to_account_info(),
+                to:recipient.clone()
+            },
+            signer_seeds,
+        ),amount);
+    }
+
+    let (Some(payment_mint),Some(recipient_token_account),Some(vault),Some(payment_token_program))=
+        (payment_mint,recipient_token_account,vault,payment_token_program) else {
+        return Err(ErrorCode::MissingPaymentAccounts.into());
+    };
+
+    let lottery_id=token_lottery.lottery_id.to_le_bytes();
+    let signer_seeds:&[&[&[u8]]]=&[&[
+        b"token_lottery".as_ref(),
+        token_lottery.creator.as_ref(),
+        lottery_id.as_ref(),
+        &[token_lottery.bump],
+    ]];
+
+    transfer_checked(CpiContext::new_with_signer(
+        payment_token_program.to_account_info(),
+        TransferChecked{
+            from:vault.to_account_info(),
+            mint:payment_mint.to_account_info(),
+            to:recipient_token_account.to_account_info(),
+            authority:token_lottery.to_account_info()
+        },
+        signer_seeds,
+    ),amount,payment_mint.decimals)
 }
 
 
@@ -683,6 +3831,417 @@ pub enum ErrorCode {
     #[msg("Incorrect ticket")] 
      IncorrectTicket ,
      #[msg("Ticket not resolved")] 
-     TicketNotResolved
+     TicketNotResolved,
+    #[msg("prize tiers must be 1..=10 non zero weights")]
+    InvalidPrizeTiers,
+    #[msg("prize tier was not drawn")]
+    InvalidPrizeTier,
+    #[msg("prize already claimed")]
+    PrizeAlreadyClaimed,
+    #[msg("no tickets sold")]
+    NoTicketsSold,
+    #[msg("lottery not cancelled")]
+    LotteryNotCancelled,
+    #[msg("not enough tickets sold for a draw")]
+    MinTicketsNotReached,
+    #[msg("all tickets sold")]
+    TicketsSoldOut,
+    #[msg("wallet reached its ticket limit")]
+    WalletTicketLimitReached,
+    #[msg("lottery start must not be after end")]
+    InvalidLotteryWindow,
+    #[msg("min tickets above max tickets")]
+    InvalidTicketLimits,
+    #[msg("ticket quantity must be above zero")]
+    InvalidTicketQuantity,
+    #[msg("ticket already refunded")]
+    TicketAlreadyRefunded,
+    #[msg("invalid branding ,check lengths ,royalty and creator shares")]
+    InvalidBranding,
+    #[msg("no authority proposed")]
+    NoPendingAuthority,
+    #[msg("draw operator must differ from the authority and treasurer")]
+    RolesNotSeparated,
+    #[msg("lottery paused")]
+    LotteryPaused,
+    #[msg("only lottery_end can be extended once tickets are sold")]
+    ConfigFrozen,
+    #[msg("instruction not allowed in the current lottery phase")]
+    InvalidLotteryPhase,
+    #[msg("not a switchboard randomness account")]
+    InvalidRandomnessAccount,
+    #[msg("randomness account belongs to another queue")]
+    IncorrectRandomnessQueue,
+    #[msg("randomness was seeded before sales closed")]
+    RandomnessCommittedTooEarly,
+    #[msg("reveal timeout must be above zero")]
+    InvalidRevealTimeout,
+    #[msg("reveal timeout not reached")]
+    RevealTimeoutNotReached,
+    #[msg("randomness replaced too many times")]
+    TooManyRecommits,
+    #[msg("crank reward above the allowed maximum")]
+    InvalidCrankReward,
+    #[msg("vault cannot pay this amount without touching its rent reserve")]
+    InsufficientVaultBalance,
+    #[msg("fee ,beneficiary ,referral and jackpot shares must stay below 100%")]
+    InvalidFee,
+    #[msg("no fees to withdraw")]
+    NoFeesAccrued,
+    #[msg("invalid beneficiaries")]
+    InvalidBeneficiaries,
+    #[msg("beneficiary account does not match")]
+    IncorrectBeneficiary,
+    #[msg("no proceeds to distribute")]
+    NoProceeds,
+    #[msg("referral account belongs to another lottery")]
+    IncorrectReferral,
+    #[msg("buyers cannot refer themselves")]
+    SelfReferral,
+    #[msg("no referral rewards to claim")]
+    NoReferralRewards,
+    #[msg("claim window must be above zero")]
+    InvalidClaimWindow,
+    #[msg("claim deadline passed")]
+    ClaimDeadlinePassed,
+    #[msg("claim deadline not reached")]
+    ClaimDeadlineNotReached,
+    #[msg("rollover destination does not match")]
+    IncorrectRolloverDestination,
+    #[msg("max redraws above the allowed maximum")]
+    InvalidRedraws,
+    #[msg("no redraw available")]
+    RedrawNotAvailable,
+    #[msg("unclaimed tiers must be redrawn first")]
+    RedrawPending,
+    #[msg("previous round not settled")]
+    RoundNotSettled,
+    #[msg("previous round does not match the template")]
+    IncorrectPreviousRound,
+    #[msg("jackpot odds must be above zero")]
+    InvalidJackpotOdds,
+    #[msg("jackpot account missing")]
+    MissingJackpotAccount,
+    #[msg("payment token accounts missing")]
+    MissingPaymentAccounts,
+    #[msg("math overflow")]
+    MathOverflow,
+    #[msg("randomness account was committed again after the lottery committed to it")]
+    RandomnessSeedChanged,
+    #[msg("this key gave up a randomness commitment and may not commit or draw again")]
+    CommitterBarred,
+    #[msg("randomness can still be recommitted")]
+    RecommitsAvailable,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn randomness(seed:u64)->[u8;32] {
+        hashv(&[b"randomness".as_ref(),seed.to_le_bytes().as_ref()]).to_bytes()
+    }
+
+    fn params()->LotteryParams {
+        LotteryParams{
+            start:0,
+            end:100,
+            price:1_000,
+            min_tickets:0,
+            max_tickets:0,
+            max_tickets_per_wallet:0,
+            prize_tiers:vec![5000,3000,2000],
+            randomness_queue:Pubkey::default(),
+            reveal_timeout_slots:150,
+            permissionless_draw:false,
+            crank_reward_bps:0,
+            fee_bps:500,
+            beneficiaries:vec![Beneficiary{wallet:Pubkey::new_unique(),share_bps:1000}],
+            referral_bps:200,
+            claim_window_slots:1_000,
+            max_redraws:0,
+            jackpot_bps:100,
+            jackpot_odds:0,
+            draw_operator:Pubkey::new_unique(),
+            treasurer:Pubkey::new_unique(),
+            guardian:Pubkey::new_unique(),
+        }
+    }
+
+    //a freshly allocated account is all zeroes ,configure fills it like lotteryinitialize does 
+    fn lottery(params:LotteryParams)->TokenLottery {
+        let mut token_lottery=TokenLottery::deserialize(&mut &[0u8;TokenLottery::INIT_SPACE][..]).unwrap();
+        token_lottery.configure(Pubkey::new_unique(),1,Pubkey::new_unique(),params);
+        token_lottery
+    }
+
+    #[test]
+    fn ticket_selection_is_uniform() {
+        //above 256 so a single byte of randomness could never reach the last tickets 
+        let ticket_count=300u64;
+        let draws=300_000u64;
 
-}
\ No newline at end of file
+        let mut counts=vec![0u64;ticket_count as usize];
+        for seed in 0..draws {
+            counts[ticket_from_randomness(&randomness(seed),ticket_count) as usize] +=1;
+        }
+
+        assert!(counts.iter().all(|count| *count > 0));
+
+        //299 degrees of freedom ,the 99.9th percentile of chi square is about 380 
+        let expected=draws as f64/ticket_count as f64;
+        let chi_square:f64=counts.iter().map(|count| {
+            let diff=*count as f64-expected;
+            diff*diff/expected
+        }).sum();
+        assert!(chi_square < 400.0,"chi square {} too high for a uniform draw",chi_square);
+    }
+
+    #[test]
+    fn ticket_selection_rejects_the_biased_tail() {
+        //2^64 mod this count is just under 2^63 so about half of all words get rejected 
+        let ticket_count=(1u64<<63)+1;
+        for seed in 0..1_000 {
+            assert!(ticket_from_randomness(&randomness(seed),ticket_count) < ticket_count);
+        }
+
+        //a word of zero sits in the tail and must be skipped for the next one 
+        let mut value=[0u8;32];
+        value[8..16].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert_eq!(ticket_from_randomness(&value,ticket_count),u64::MAX % ticket_count);
+    }
+
+    #[test]
+    fn draw_winners_are_distinct() {
+        for seed in 0..100 {
+            let winners=draw_winners(&randomness(seed),10,10,&[]);
+            let mut sorted=winners.clone();
+            sorted.sort();
+            assert_eq!(sorted,(0..10).collect::<Vec<u64>>());
+        }
+    }
+
+    #[test]
+    fn redraw_skips_previously_drawn_tickets() {
+        for seed in 0..100 {
+            let first=draw_winners(&randomness(seed),10,3,&[]);
+            let redraw=draw_winners(&randomness(seed+1000),10,7,&first);
+            assert!(redraw.iter().all(|ticket| !first.contains(ticket)));
+
+            let mut all=[first,redraw].concat();
+            all.sort();
+            assert_eq!(all,(0..10).collect::<Vec<u64>>());
+        }
+    }
+
+    #[test]
+    fn deposit_splits_the_ticket_waterfall() {
+        let mut token_lottery=lottery(params());
+
+        let referral_reward=token_lottery.deposit(10_000,true).unwrap();
+        assert_eq!(referral_reward,200);
+        assert_eq!(token_lottery.fees_accrued,500);
+        assert_eq!(token_lottery.beneficiary_proceeds,1_000);
+        assert_eq!(token_lottery.referral_rewards,200);
+        assert_eq!(token_lottery.jackpot_contribution,100);
+        assert_eq!(token_lottery.token_lottery_pot,8_200);
+
+        //without a referrer the referral share stays in the pot 
+        assert_eq!(token_lottery.deposit(10_000,false).unwrap(),0);
+        assert_eq!(token_lottery.referral_rewards,200);
+        assert_eq!(token_lottery.token_lottery_pot,8_200+8_400);
+    }
+
+    #[test]
+    fn cancel_folds_every_share_back_for_refunds() {
+        let mut token_lottery=lottery(params());
+        token_lottery.deposit(10_000,true).unwrap();
+        token_lottery.deposit(10_000,false).unwrap();
+
+        token_lottery.cancel();
+        assert_eq!(token_lottery.phase,LotteryPhase::Cancelled);
+        assert_eq!(token_lottery.token_lottery_pot,20_000);
+        assert_eq!(token_lottery.fees_accrued+token_lottery.beneficiary_proceeds+token_lottery.referral_rewards+token_lottery.jackpot_contribution,0);
+    }
+
+    #[test]
+    fn last_beneficiary_takes_the_rounding_dust() {
+        let mut params=params();
+        params.beneficiaries=(0..3).map(|_| Beneficiary{wallet:Pubkey::new_unique(),share_bps:1000}).collect();
+        let mut token_lottery=lottery(params);
+        token_lottery.beneficiary_proceeds=100;
+
+        assert_eq!(token_lottery.beneficiary_amounts(),vec![33,33,34]);
+    }
+
+    #[test]
+    fn undersubscribed_lottery_refunds_capped_by_the_pot() {
+        let mut params=params();
+        params.min_tickets=3;
+        let mut token_lottery=lottery(params);
+        token_lottery.phase=LotteryPhase::Open;
+        token_lottery.ticket_number=2;
+        //a transfer fee mint left the pot short of two full tickets 
+        token_lottery.token_lottery_pot=1_500;
+
+        assert!(token_lottery.take_refund(1,100).is_err());
+        assert_eq!(token_lottery.take_refund(1,101).unwrap(),1_000);
+        assert_eq!(token_lottery.phase,LotteryPhase::Cancelled);
+        assert_eq!(token_lottery.take_refund(1,101).unwrap(),500);
+        assert_eq!(token_lottery.token_lottery_pot,0);
+    }
+
+    #[test]
+    fn refunds_never_touch_the_seed_jackpot() {
+        let mut token_lottery=lottery(params());
+        token_lottery.token_lottery_pot=5_000;
+        token_lottery.seed_jackpot=5_000;
+        token_lottery.deposit(1_000,false).unwrap();
+        token_lottery.cancel();
+
+        assert_eq!(token_lottery.take_refund(2,0).unwrap(),1_000);
+        assert_eq!(token_lottery.take_refund(1,0).unwrap(),0);
+        assert_eq!(token_lottery.token_lottery_pot,5_000);
+    }
+
+    fn drawn(prize_tiers:Vec<u16>,winners:Vec<u64>,prize_pool:u64)->TokenLottery {
+        let mut params=params();
+        params.prize_tiers=prize_tiers;
+        let mut token_lottery=lottery(params);
+        token_lottery.phase=LotteryPhase::Drawn;
+        token_lottery.ticket_number=10;
+        token_lottery.tier_claimed=vec![false;winners.len()];
+        token_lottery.drawn_tickets=winners.clone();
+        token_lottery.winners=winners;
+        token_lottery.prize_pool=prize_pool;
+        token_lottery.token_lottery_pot=prize_pool;
+        token_lottery.claim_deadline=50;
+        token_lottery
+    }
+
+    #[test]
+    fn claiming_every_tier_settles_the_lottery() {
+        let mut token_lottery=drawn(vec![5000,3000,2000],vec![3,7,1],1_000);
+
+        assert_eq!(token_lottery.claim_tier(1,50).unwrap(),300);
+        assert_eq!(token_lottery.claim_tier(1,50).unwrap_err(),ErrorCode::PrizeAlreadyClaimed.into());
+        assert_eq!(token_lottery.claim_tier(0,51).unwrap_err(),ErrorCode::ClaimDeadlinePassed.into());
+        assert_eq!(token_lottery.claim_tier(3,10).unwrap_err(),ErrorCode::InvalidPrizeTier.into());
+
+        assert_eq!(token_lottery.claim_tier(0,10).unwrap(),500);
+        assert_eq!(token_lottery.phase,LotteryPhase::Drawn);
+        assert_eq!(token_lottery.claim_tier(2,10).unwrap(),200);
+        assert_eq!(token_lottery.phase,LotteryPhase::Settled);
+        assert_eq!(token_lottery.token_lottery_pot,0);
+    }
+
+    #[test]
+    fn redraw_needs_budget_and_enough_undrawn_tickets() {
+        let mut token_lottery=drawn(vec![5000,3000,2000],vec![3,7,1],1_000);
+        token_lottery.tier_claimed[2]=true;
+        assert!(!token_lottery.can_redraw());
+
+        token_lottery.max_redraws=1;
+        assert!(token_lottery.can_redraw());
+
+        //two tiers open but only one ticket was never drawn 
+        token_lottery.ticket_number=4;
+        assert!(!token_lottery.can_redraw());
+
+        token_lottery.ticket_number=10;
+        token_lottery.redraws=1;
+        assert!(!token_lottery.can_redraw());
+    }
+
+    #[test]
+    fn jackpot_joins_the_first_tier_only() {
+        let mut token_lottery=drawn(vec![1,1,1],vec![3,7,1],900);
+        token_lottery.jackpot_prize=50;
+        token_lottery.token_lottery_pot+=50;
+
+        assert_eq!(token_lottery.tier_prize(0).unwrap(),350);
+        assert_eq!(token_lottery.tier_prize(1).unwrap(),300);
+        assert_eq!(token_lottery.tier_prize(2).unwrap(),300);
+    }
+
+    #[test]
+    fn fewer_winners_than_tiers_split_the_whole_pool() {
+        let token_lottery=drawn(vec![5000,3000,2000],vec![3,7],800);
+
+        assert_eq!(token_lottery.tier_prize(0).unwrap(),500);
+        assert_eq!(token_lottery.tier_prize(1).unwrap(),300);
+    }
+
+    #[test]
+    fn commitment_can_only_be_given_up_after_the_reveal_timeout() {
+        let mut token_lottery=lottery(params());
+        token_lottery.randomness_committed_slot=200;
+
+        assert_eq!(token_lottery.check_reveal_timeout(350).unwrap_err(),ErrorCode::RevealTimeoutNotReached.into());
+        assert!(token_lottery.check_reveal_timeout(351).is_ok());
+
+        token_lottery.randomness_committed_slot=u64::MAX;
+        assert_eq!(token_lottery.check_reveal_timeout(u64::MAX).unwrap_err(),ErrorCode::MathOverflow.into());
+    }
+
+    #[test]
+    fn committers_of_abandoned_randomness_never_draw() {
+        let mut token_lottery=lottery(params());
+        let draw_operator=token_lottery.draw_operator;
+        let stranger=Pubkey::new_unique();
+        assert!(token_lottery.can_draw_committed(draw_operator));
+        assert!(!token_lottery.can_draw_committed(stranger));
+
+        token_lottery.abandoned_committers.push(draw_operator);
+        assert!(!token_lottery.can_draw_committed(draw_operator));
+        assert!(token_lottery.can_draw_committed(stranger));
+    }
+
+    #[test]
+    fn round_without_tickets_is_undersubscribed() {
+        let mut token_lottery=lottery(params());
+        assert!(!token_lottery.is_undersubscribed(100));
+        assert!(token_lottery.is_undersubscribed(101));
+
+        token_lottery.phase=LotteryPhase::Open;
+        token_lottery.ticket_number=1;
+        assert!(!token_lottery.is_undersubscribed(101));
+    }
+
+    #[test]
+    fn unpause_only_returns_slots_paused_after_the_draw() {
+        //drawn at slot 40 with a 1_000 slot window 
+        let mut token_lottery=drawn(vec![5000,3000,2000],vec![3,7,1],1_000);
+        token_lottery.claim_deadline=1_040;
+
+        token_lottery.paused_at=10;
+        token_lottery.extend_claim_deadline(100).unwrap();
+        assert_eq!(token_lottery.claim_deadline,1_100);
+
+        token_lottery.paused_at=200;
+        token_lottery.extend_claim_deadline(250).unwrap();
+        assert_eq!(token_lottery.claim_deadline,1_150);
+
+        let mut undrawn=lottery(params());
+        undrawn.extend_claim_deadline(250).unwrap();
+        assert_eq!(undrawn.claim_deadline,0);
+    }
+
+    #[test]
+    fn draw_operator_is_never_admin_or_treasurer() {
+        let authority=Pubkey::new_unique();
+        let mut params=params();
+        assert!(params.check_roles(authority).is_ok());
+
+        params.treasurer=authority;
+        assert!(params.check_roles(authority).is_ok());
+
+        params.draw_operator=authority;
+        assert_eq!(params.check_roles(authority).unwrap_err(),ErrorCode::RolesNotSeparated.into());
+
+        params.draw_operator=Pubkey::new_unique();
+        params.treasurer=params.draw_operator;
+        assert_eq!(params.check_roles(authority).unwrap_err(),ErrorCode::RolesNotSeparated.into());
+    }
+}
@@ -23,8 +23,22 @@ describe("token-lottery", () => {
   let switchboardprogram ;
   const rngkp=anchor.web3.Keypair.generate() ;
 
+  //commits and draws ,kept apart from the wallet that administers the lottery and holds its funds 
+  const drawOperator=anchor.web3.Keypair.generate() ;
+
   const TOKEN_METADATA_PROGRAM_ID=new anchor.web3.PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
 
+  //randomness committed for the draw has to come from this switchboard queue 
+  const queue=new anchor.web3.PublicKey("A43DyUGA7s8eXPxqEjJY6EBu1KKbNgfxF8h17VAHn13w");
+
+  //every lottery is keyed by its creator and an id 
+  const lotteryId=new anchor.BN(1);
+
+  const tokenLottery=anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("token_lottery"),wallet.publicKey.toBuffer(),lotteryId.toArrayLike(Buffer,"le",8)],
+    program.programId
+  )[0];
+
 
  before("before switchboard program load",async()=>{
   const switchboardidl=await anchor.Program.fetchIdl(
@@ -42,6 +56,7 @@ describe("token-lottery", () => {
   async function buyTicket(){
     const buyTicketix=await program.methods.buyTicket().accounts(
       {
+        tokenLottery:tokenLottery,
         tokenProgram:TOKEN_PROGRAM_ID
       }
     ).instruction() ;
@@ -85,7 +100,7 @@ describe("token-lottery", () => {
 
 
     const mint=anchor.web3.PublicKey.findProgramAddressSync(
-      [Buffer.from("collection_mint")] ,
+      [Buffer.from("collection_mint"),tokenLottery.toBuffer()] ,
       program.programId,
     )[0];
 
@@ -103,15 +118,45 @@ describe("token-lottery", () => {
 
 
     const initConfigIx=await program.methods.configinitialize(
-      new anchor.BN(0),
-      new anchor.BN(slot +10),
-      new anchor.BN(10000)
+      lotteryId,
+      {
+        start:new anchor.BN(0),
+        end:new anchor.BN(slot +10),
+        price:new anchor.BN(10000),
+        minTickets:new anchor.BN(1),
+        maxTickets:new anchor.BN(0),
+        maxTicketsPerWallet:new anchor.BN(0),
+        prizeTiers:[5000,3000,2000],
+        randomnessQueue:queue,
+        revealTimeoutSlots:new anchor.BN(150),
+        permissionlessDraw:false,
+        crankRewardBps:0,
+        feeBps:500,
+        beneficiaries:[],
+        referralBps:200,
+        claimWindowSlots:new anchor.BN(216000),
+        maxRedraws:0,
+        jackpotBps:0,
+        jackpotOdds:new anchor.BN(0),
+        drawOperator:drawOperator.publicKey,
+        treasurer:wallet.publicKey,
+        guardian:wallet.publicKey
+      }
     ).instruction();
 
 
-    const initLottery=await program.methods.lotteryinitalize()
+    //empty branding falls back to the default name ,symbol and uri 
+    const initLottery=await program.methods.lotteryinitalize({
+      name:"",
+      symbol:"",
+      collectionUri:"",
+      ticketBaseUri:"",
+      sellerFeeBasisPoints:0,
+      creators:[]
+    })
     .accounts(
       {
+        tokenLottery:tokenLottery,
         masterEdition:masteredition,
         metadata:metadata,
         tokenProgram:TOKEN_PROGRAM_ID
@@ -145,11 +190,21 @@ describe("token-lottery", () => {
     await buyTicket();
     await buyTicket()
    })
+
+   it("buying tickets in bulk",async()=>{
+    //tickets 5..14 are held in a single bundle account instead of nfts 
+    const signature=await program.methods.buyTickets(new anchor.BN(10)).accounts(
+      {
+        tokenLottery:tokenLottery
+      }
+    ).rpc();
+
+    console.log("buy tickets ",signature);
+   })
    
           
 
    it("is commiting a reveal a winner",async()=>{
-    const queue=new anchor.web3.PublicKey("A43DyUGA7s8eXPxqEjJY6EBu1KKbNgfxF8h17VAHn13w");
     const queueaccount=new sb.Queue(switchboardprogram,queue);
 
    console.log("queue account",queue.toString());
@@ -194,6 +249,8 @@ process.exit(1)
    const commitinstruction=await program.methods.commitWinner()
    .accounts(
     {
+      payer:drawOperator.publicKey,
+      tokenLottery:tokenLottery,
       randomnessAccountData:randomness.pubkey
     }
    ).instruction()
@@ -202,7 +259,7 @@ process.exit(1)
     connection:switchboardprogram.provider.connection,
     ixs:[sbCommitix,commitinstruction],
     payer:wallet.publicKey,
-    signers:[wallet.payer],
+    signers:[wallet.payer,drawOperator],
     computeUnitPrice:75_000,
     computeUnitLimitMultiple:1.3
 
@@ -221,6 +278,8 @@ process.exit(1)
    const revealsbinstruction=await randomness.revealIx();
 
    const revealinstructio=await program.methods.chooseWinner().accounts({
+    payer:drawOperator.publicKey,
+    tokenLottery:tokenLottery,
     randomnessAccountData:randomness.pubkey
    }).instruction();
 
@@ -230,7 +289,7 @@ process.exit(1)
     connection:switchboardprogram.provider.connection,
     ixs:[revealinstructio,revealsbinstruction],
     payer:wallet.publicKey,
-    signers:[wallet.payer],
+    signers:[wallet.payer,drawOperator],
     computeUnitPrice:75_000,
     computeUnitLimitMultiple:1.3
    })
@@ -253,16 +312,10 @@ console.log("reveal signature ",revealsignature);
 
    it("is claiming a prize",async()=>{
 
-    const tokenlotteryaddress=anchor.web3.PublicKey.findProgramAddressSync(
-      [Buffer.from("token_lottery")],
-      program.programId
-    )[0]
-
+    const lotteryconfid=await program.account.tokenLottery.fetch(tokenLottery) ;
 
-    const lotteryconfid=await program.account.tokenLottery.fetch(tokenlotteryaddress) ;
 
-
-    console.log("lottery config winner",lotteryconfid.winner);
+    console.log("lottery config winners",lotteryconfid.winners);
     console.log("lottery config ",lotteryconfid);
    
 
@@ -276,8 +329,26 @@ console.log("reveal signature ",revealsignature);
     })
 
 
+    //the wallet bought every ticket so it holds each winning tier 
+    for (let tier=0;tier<lotteryconfid.winners.length;tier++){
+    if (new anchor.BN(lotteryconfid.winners[tier]).gten(5)){
+      const ticketBundle=anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("ticket_bundle"),tokenLottery.toBuffer(),new anchor.BN(5).toArrayLike(Buffer,"le",8)],
+        program.programId
+      )[0];
+
+      const bundlesig=await program.methods.claimBundlePrize(tier).accounts(
+        {
+          tokenLottery:tokenLottery,
+          ticketBundle:ticketBundle
+        }
+      ).rpc();
+      console.log(bundlesig)
+      continue;
+    }
+
     const winningmint=anchor.web3.PublicKey.findProgramAddressSync(
-      [new anchor.BN(lotteryconfid.winner).toArrayLike(Buffer,"le",8)],
+      [tokenLottery.toBuffer(),new anchor.BN(lotteryconfid.winners[tier]).toArrayLike(Buffer,"le",8)],
       program.programId
     )[0];
 
@@ -292,8 +363,9 @@ console.log("reveal signature ",revealsignature);
      console.log("winnig token address",winningtokenaddress.toBase58()) ;
 
 
-     const claiminstruction=await program.methods.claimPrize().accounts(
+     const claiminstruction=await program.methods.claimPrize(tier).accounts(
       {
+       tokenLottery:tokenLottery,
        tokenProgram:TOKEN_PROGRAM_ID
       }
      ).instruction() ;
@@ -312,9 +384,84 @@ console.log("reveal signature ",revealsignature);
 
      const claimsig=await anchor.web3.sendAndConfirmTransaction(connection,claimtransaction,[wallet.payer]);
      console.log(claimsig)
+    }
 
 
    })
+
+   it("is withdrawing fees",async()=>{
+    //the wallet is still the treasurer ,feeBps of every ticket was kept out of the pot 
+    const signature=await program.methods.withdrawFees().accounts(
+      {
+        tokenLottery:tokenLottery
+      }
+    ).rpc();
+
+    console.log("withdraw fees ",signature);
+   })
+
+   it("is starting a recurring round",async()=>{
+    const templateId=new anchor.BN(1);
+
+    const roundTemplate=anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("round_template"),wallet.publicKey.toBuffer(),templateId.toArrayLike(Buffer,"le",8)],
+      program.programId
+    )[0];
+
+    //rounds are lotteries created by the template ,the round number is their lottery id 
+    const firstRound=anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("token_lottery"),roundTemplate.toBuffer(),new anchor.BN(1).toArrayLike(Buffer,"le",8)],
+      program.programId
+    )[0];
+
+    const templatesig=await program.methods.createRoundTemplate(
+      templateId,
+      new anchor.BN(1000),
+      {
+        start:new anchor.BN(0),
+        end:new anchor.BN(0),
+        price:new anchor.BN(10000),
+        minTickets:new anchor.BN(1),
+        maxTickets:new anchor.BN(0),
+        maxTicketsPerWallet:new anchor.BN(0),
+        prizeTiers:[10000],
+        randomnessQueue:queue,
+        revealTimeoutSlots:new anchor.BN(150),
+        permissionlessDraw:true,
+        crankRewardBps:50,
+        feeBps:500,
+        beneficiaries:[],
+        referralBps:0,
+        claimWindowSlots:new anchor.BN(216000),
+        maxRedraws:0,
+        jackpotBps:0,
+        jackpotOdds:new anchor.BN(0),
+        drawOperator:drawOperator.publicKey,
+        treasurer:wallet.publicKey,
+        guardian:wallet.publicKey
+      },
+      {
+        name:"",
+        symbol:"",
+        collectionUri:"",
+        ticketBaseUri:"",
+        sellerFeeBasisPoints:0,
+        creators:[]
+      }
+    ).rpc();
+    console.log("create round template ",templatesig);
+
+    const roundsig=await program.methods.startNextRound().accounts(
+      {
+        roundTemplate:roundTemplate,
+        previousLottery:null,
+        previousSolVault:null,
+        previousVault:null,
+        tokenLottery:firstRound
+      }
+    ).rpc();
+    console.log("start next round ",roundsig);
+   })
  
    
 